@@ -3,7 +3,7 @@ extern crate iterative_json_parser;
 use std::fs::File;
 use std::io::Read;
 
-use iterative_json_parser::source::string::VecSource;
+use iterative_json_parser::source::string::SliceSource;
 use iterative_json_parser::sink::into_enum::EnumSink;
 use iterative_json_parser::TokenizerState;
 use iterative_json_parser::input::{SourceSink};
@@ -14,7 +14,7 @@ fn main() {
 
     for _ in 0..100 {
         let mut ss = SourceSink {
-            source: VecSource::new(data.clone()),
+            source: SliceSource::new(&data),
             sink: EnumSink::new(&data),
         };
         let mut parser = TokenizerState::new();
@@ -1,17 +1,365 @@
-use ::parser::ParserState;
+use std::io::Read;
+
+use ::Bailable;
+use ::error::{Error, ParseError, Unexpected};
+use ::input::{SourceSink, BailVariant, Pos, Range, Window};
+use ::sink::{Sink, NumberData, Position, StringPosition, StringKind};
+use ::sink::into_enum::{EnumSink, Json};
+use ::sink::value::{Value, ValueSink, ValueError};
+use ::sink::borrowed::{BorrowedValue, BorrowedValueSink};
+use ::sink::tape::{Tape, TapeSink};
+use ::sink::pointer::{Pointer, PointerSink, PointerBail};
+use ::sink::query::{Query, QuerySink, QueryBail};
+use ::source::Source;
+use ::source::string::SliceSource;
+use ::source::stream::StreamSource;
 use ::tokenizer::TokenizerState;
+use ::options::{Options, Documents};
 
-struct Decoder {
-    parser: ParserState,
-    tokenizer: TokenizerState,
+/// High level entry point, parses a complete document in one call.
+///
+/// Takes care of pairing the source with the sink, and of running the
+/// tokenizer until the document is done. Source bails are handled
+/// internally, sink bails abort parsing and are returned as `Error::Sink`.
+#[derive(Debug, Clone, Default)]
+pub struct Decoder {
+    options: Options,
 }
 
 impl Decoder {
-    fn new() -> Self {
+
+    pub fn new() -> Decoder {
+        Decoder::with_options(Options::default())
+    }
+
+    pub fn with_options(options: Options) -> Decoder {
         Decoder {
-            parser: ParserState::new(),
-            tokenizer: TokenizerState::new(),
+            options,
+        }
+    }
+
+    pub fn parse_slice(data: &[u8]) -> Result<Json, Error> {
+        Decoder::new().decode_slice(data)
+    }
+
+    pub fn parse_reader<R>(reader: R) -> Result<Json, Error> where R: Read {
+        Decoder::new().decode_reader(reader)
+    }
+
+    // The methods returning a single value always parse a single document,
+    // whatever `Options::documents` says.
+    fn single(&self) -> Decoder {
+        Decoder::with_options(Options {
+            documents: Documents::Single,
+            ..self.options
+        })
+    }
+
+    pub fn decode_slice(&self, data: &[u8]) -> Result<Json, Error> {
+        let sink = self.single().decode_with(data, EnumSink::new(data))?;
+        Ok(sink.to_result())
+    }
+
+    pub fn decode_reader<R>(&self, reader: R) -> Result<Json, Error> where R: Read {
+        let sink = self.single().decode_reader_with(reader, EnumSink::new_windowed())?;
+        Ok(sink.to_result())
+    }
+
+    pub fn decode_value(&self, data: &[u8]) -> Result<Value, Error<ValueError>> {
+        let (sink, end) = self.single().parse_slice_with(data, ValueSink::new())?;
+        root(sink.into_value(), end)
+    }
+
+    /// Like `decode_value`, but strings without escapes borrow from `data`.
+    pub fn decode_borrowed<'a>(&self, data: &'a [u8]) -> Result<BorrowedValue<'a>, Error<ValueError>> {
+        let (sink, end) = self.single().parse_slice_with(data, BorrowedValueSink::new(data))?;
+        root(sink.into_value(), end)
+    }
+
+    pub fn decode_tape<'a>(&self, data: &'a [u8]) -> Result<Tape<'a>, Error<ValueError>> {
+        let (sink, end) = self.single().parse_slice_with(data, TapeSink::new(data))?;
+        root(sink.into_tape(), end)
+    }
+
+    pub fn decode_value_reader<R>(&self, reader: R) -> Result<Value, Error<ValueError>> where R: Read {
+        let (sink, end) = self.single().parse_reader_with(reader, ValueSink::new())?;
+        root(sink.into_value(), end)
+    }
+
+    /// The value `pointer` refers to, or `None` if there is none. Parsing
+    /// stops right after the value, so the rest of the input is not
+    /// checked. Neither are the values skipped on the way to it, beyond
+    /// their brackets and quotes, see `Sink::skip_value`.
+    pub fn decode_pointer(&self, data: &[u8], pointer: &Pointer) -> Result<Option<Value>, Error<ValueError>> {
+        let mut sink = PointerSink::new(pointer, ValueSink::new());
+        let result = self.single().decode_with(data, &mut sink).map(|_| ());
+        pointer_result(result, sink)
+    }
+
+    /// Like `decode_pointer`, but stops reading once the value is found.
+    pub fn decode_pointer_reader<R>(&self, reader: R, pointer: &Pointer) -> Result<Option<Value>, Error<ValueError>> where R: Read {
+        let mut sink = PointerSink::new(pointer, ValueSink::new());
+        let result = self.single().decode_reader_with(reader, &mut sink).map(|_| ());
+        pointer_result(result, sink)
+    }
+
+    /// The values matching `query`, in document order.
+    pub fn decode_query(&self, data: &[u8], query: &Query) -> Result<Vec<Value>, Error<ValueError>> {
+        let mut values = Vec::new();
+        let sink = QuerySink::new(query, |value| {
+            values.push(value);
+            Ok::<(), ()>(())
+        });
+        match self.single().decode_with(data, sink) {
+            Ok(_) => Ok(values),
+            Err(Error::Sink(QueryBail::Value(err))) => Err(Error::Sink(err)),
+            Err(Error::Sink(QueryBail::Callback(()))) => unreachable!(),
+            Err(Error::Unexpected(pos, reason)) => Err(Error::Unexpected(pos, reason)),
+            Err(Error::Io(err)) => Err(Error::Io(err)),
+        }
+    }
+
+    /// Parses `data` as JSON Lines, with one value per line. Blank lines are
+    /// ignored, and a bad line does not stop the lines after it from being
+    /// parsed.
+    pub fn decode_lines(&self, data: &[u8]) -> Vec<Result<Value, Error<ValueError>>> {
+        self.decode_documents(data, Documents::Lines)
+    }
+
+    /// Like `decode_lines`, but reads one line at a time. Stops at the first
+    /// I/O error, which is the last result.
+    pub fn decode_lines_reader<R>(&self, reader: R) -> Vec<Result<Value, Error<ValueError>>> where R: Read {
+        self.decode_documents_reader(reader, Documents::Lines)
+    }
+
+    /// The value of each document in `data`, separated as `documents` says.
+    /// An error that stops parsing, as with `Documents::Concatenated`, is the
+    /// last result.
+    pub fn decode_documents(&self, data: &[u8], documents: Documents) -> Vec<Result<Value, Error<ValueError>>> {
+        if documents == Documents::Single {
+            return vec![self.decode_value(data)];
+        }
+        let decoder = Decoder::with_options(Options {
+            documents,
+            ..self.options
+        });
+        let mut sink = DocumentsSink::new();
+        let result = decoder.decode_with(data, &mut sink).map(|_| ());
+        sink.finish(result)
+    }
+
+    /// Like `decode_documents`, but reads one document at a time.
+    pub fn decode_documents_reader<R>(&self, reader: R, documents: Documents) -> Vec<Result<Value, Error<ValueError>>> where R: Read {
+        if documents == Documents::Single {
+            return vec![self.decode_value_reader(reader)];
         }
+        let decoder = Decoder::with_options(Options {
+            documents,
+            ..self.options
+        });
+        let mut sink = DocumentsSink::new();
+        let result = decoder.decode_reader_with(reader, &mut sink).map(|_| ());
+        sink.finish(result)
     }
 
+    /// Parses `data` into `sink`, returning the sink when done.
+    ///
+    /// Pass `&mut sink` to keep hold of the sink when parsing is aborted by
+    /// a sink bail.
+    pub fn decode_with<S>(&self, data: &[u8], sink: S) -> Result<S, Error<S::Bail>> where S: Sink {
+        self.parse_slice_with(data, sink).map(|(sink, _)| sink)
+    }
+
+    /// Parses everything read from `reader` into `sink`. Only the part of
+    /// the input that is currently being parsed is held in memory.
+    pub fn decode_reader_with<R, S>(&self, reader: R, sink: S) -> Result<S, Error<S::Bail>> where R: Read, S: Sink {
+        self.parse_reader_with(reader, sink).map(|(sink, _)| sink)
+    }
+
+    // Like `decode_with`, but also returns where parsing stopped.
+    fn parse_slice_with<S>(&self, data: &[u8], sink: S) -> Result<(S, Pos), Error<S::Bail>> where S: Sink {
+        let mut ss = SourceSink {
+            source: SliceSource::new(data),
+            sink,
+        };
+        let mut tokenizer = TokenizerState::with_options(self.options);
+
+        match tokenizer.run(&mut ss) {
+            Ok(()) => Ok((ss.sink, ss.source.position())),
+            Err(err) => Err(lift_error(err)),
+        }
+    }
+
+    // Like `decode_reader_with`, but also returns where parsing stopped.
+    fn parse_reader_with<R, S>(&self, reader: R, sink: S) -> Result<(S, Pos), Error<S::Bail>> where R: Read, S: Sink {
+        let mut ss = SourceSink {
+            source: StreamSource::new(reader),
+            sink,
+        };
+        let mut tokenizer = TokenizerState::with_options(self.options);
+
+        loop {
+            match tokenizer.run(&mut ss) {
+                Ok(()) => return Ok((ss.sink, ss.source.position())),
+                Err(ParseError::SourceBail(BailVariant::Source(()))) => {
+                    let keep = tokenizer.retain_from().unwrap_or(ss.source.position());
+                    ss.source.discard_before(keep);
+                    ss.source.fill()?;
+                },
+                Err(err) => return Err(lift_error(err)),
+            }
+        }
+    }
+
+}
+
+// Collects the value or error of each document. A `ValueSink` bail only
+// ends its own document.
+#[derive(Debug)]
+struct DocumentsSink {
+    sink: ValueSink,
+    error: Option<ValueError>,
+    results: Vec<Result<Value, Error<ValueError>>>,
+}
+
+impl DocumentsSink {
+    fn new() -> DocumentsSink {
+        DocumentsSink {
+            sink: ValueSink::new(),
+            error: None,
+            results: Vec::new(),
+        }
+    }
+
+    fn finish(mut self, result: Result<(), Error<()>>) -> Vec<Result<Value, Error<ValueError>>> {
+        match result {
+            Ok(()) => (),
+            Err(Error::Unexpected(pos, reason)) => self.results.push(Err(Error::Unexpected(pos, reason))),
+            Err(Error::Io(err)) => self.results.push(Err(Error::Io(err))),
+            Err(Error::Sink(())) => unreachable!(),
+        }
+        self.results
+    }
+
+    fn lift<F>(&mut self, event: F) -> Result<(), ()>
+        where F: FnOnce(&mut ValueSink) -> Result<(), ValueError>
+    {
+        if self.error.is_none() {
+            self.error = event(&mut self.sink).err();
+        }
+        Ok(())
+    }
+
+    fn forward<F>(&mut self, event: F) where F: FnOnce(&mut ValueSink) {
+        if self.error.is_none() {
+            event(&mut self.sink);
+        }
+    }
+}
+
+impl Bailable for DocumentsSink {
+    type Bail = ();
+}
+
+impl Sink for DocumentsSink {
+    fn push_map(&mut self, pos: Position) {
+        self.forward(|sink| sink.push_map(pos))
+    }
+    fn push_array(&mut self, pos: Position) {
+        self.forward(|sink| sink.push_array(pos))
+    }
+
+    fn push_number(&mut self, pos: Position, number: NumberData) -> Result<(), Self::Bail> {
+        self.lift(|sink| sink.push_number(pos, number))
+    }
+    fn push_number_window(&mut self, pos: Position, number: NumberData, window: Window) -> Result<(), Self::Bail> {
+        self.lift(|sink| sink.push_number_window(pos, number, window))
+    }
+    fn push_bool(&mut self, pos: Position, boolean: bool) -> Result<(), Self::Bail> {
+        self.lift(|sink| sink.push_bool(pos, boolean))
+    }
+    fn push_null(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        self.lift(|sink| sink.push_null(pos))
+    }
+
+    fn start_string(&mut self, pos: StringPosition) {
+        self.forward(|sink| sink.start_string(pos))
+    }
+    fn start_string_kind(&mut self, pos: StringPosition, kind: StringKind) {
+        self.forward(|sink| sink.start_string_kind(pos, kind))
+    }
+    fn append_string_range(&mut self, string: Range) {
+        self.forward(|sink| sink.append_string_range(string))
+    }
+    fn append_string_window(&mut self, string: Range, window: Window) {
+        self.forward(|sink| sink.append_string_window(string, window))
+    }
+    fn append_string_single(&mut self, character: u8) {
+        self.forward(|sink| sink.append_string_single(character))
+    }
+    fn append_string_codepoint(&mut self, codepoint: char) {
+        self.forward(|sink| sink.append_string_codepoint(codepoint))
+    }
+    fn finalize_string(&mut self, pos: StringPosition) -> Result<(), Self::Bail> {
+        self.lift(|sink| sink.finalize_string(pos))
+    }
+
+    fn finalize_array(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        self.lift(|sink| sink.finalize_array(pos))
+    }
+    fn finalize_map(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        self.lift(|sink| sink.finalize_map(pos))
+    }
+
+    fn pop_into_map(&mut self) {
+        self.forward(|sink| sink.pop_into_map())
+    }
+    fn pop_into_array(&mut self) {
+        self.forward(|sink| sink.pop_into_array())
+    }
+
+    fn start_document(&mut self, _start: Pos) {
+        self.sink = ValueSink::new();
+        self.error = None;
+    }
+    fn end_document(&mut self, _document: Range) -> Result<(), Self::Bail> {
+        let sink = ::std::mem::replace(&mut self.sink, ValueSink::new());
+        self.results.push(match self.error.take() {
+            Some(err) => Err(Error::Sink(err)),
+            None => Ok(sink.into_value().unwrap()),
+        });
+        Ok(())
+    }
+    fn document_error(&mut self, _document: Range, pos: Pos, reason: Unexpected) -> Result<(), Self::Bail> {
+        self.results.push(Err(Error::Unexpected(pos, reason)));
+        Ok(())
+    }
+}
+
+// The value of a single document. A parsed document always has one, but a
+// missing value is reported rather than trusted away.
+fn root<T, B>(value: Option<T>, end: Pos) -> Result<T, Error<B>> {
+    value.ok_or(Error::Unexpected(end, Unexpected::Eof))
+}
+
+fn pointer_result(result: Result<(), Error<PointerBail<ValueError>>>, sink: PointerSink<ValueSink>) -> Result<Option<Value>, Error<ValueError>> {
+    match result {
+        // The document ended without the value being found.
+        Ok(()) => Ok(None),
+        Err(Error::Sink(PointerBail::Done)) => Ok(sink.into_inner().into_value()),
+        Err(Error::Sink(PointerBail::Inner(err))) => Err(Error::Sink(err)),
+        Err(Error::Unexpected(pos, reason)) => Err(Error::Unexpected(pos, reason)),
+        Err(Error::Io(err)) => Err(Error::Io(err)),
+    }
+}
+
+fn lift_error<SourceBail, SinkBail>(err: ParseError<BailVariant<SourceBail, SinkBail>>) -> Error<SinkBail> {
+    match err {
+        ParseError::Unexpected(pos, reason) => Error::Unexpected(pos, reason),
+        ParseError::SourceBail(BailVariant::Sink(bail)) => Error::Sink(bail),
+        // Not returned from a non-bailing source, or handled by the caller.
+        ParseError::SourceBail(BailVariant::Source(_)) => unreachable!(),
+        // The tokenizer only returns these internally.
+        ParseError::End | ParseError::Eof => unreachable!(),
+    }
 }
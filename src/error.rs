@@ -1,3 +1,5 @@
+use std::{error, fmt, io};
+
 use ::input::Pos;
 
 #[derive(Debug, PartialEq)]
@@ -27,12 +29,25 @@ pub enum Unexpected {
     Bool,
     Null,
     Quote,
+    TrailingComma,
+    PlusSign,
+    LeadingZero,
+    EmptyFraction,
+    EmptyExponent,
+    DepthLimit,
+    StringLimit,
+    NumberLimit,
+    MemberLimit,
+    DocumentLimit,
 
     // Tokenizer
     InvalidUtf8,
     InvalidEscape,
     InvalidEscapeHex,
+    InvalidLiteral,
     Character,
+    LineEnd,
+    Truncated,
     Eof,
 }
 
@@ -54,12 +69,124 @@ impl Unexpected {
             U::Bool => "unexpected boolean",
             U::Null => "unexpected null",
             U::Quote => "unexpected \"",
+            U::TrailingComma => "unexpected trailing comma",
+            U::PlusSign => "unexpected + in front of number",
+            U::LeadingZero => "unexpected leading zero in number",
+            U::EmptyFraction => "expected digits after decimal point",
+            U::EmptyExponent => "expected digits in exponent",
+            U::DepthLimit => "maximum nesting depth exceeded",
+            U::StringLimit => "maximum string length exceeded",
+            U::NumberLimit => "maximum number length exceeded",
+            U::MemberLimit => "maximum number of members exceeded",
+            U::DocumentLimit => "maximum document size exceeded",
 
             U::InvalidUtf8 => "expected valid utf8 data",
             U::InvalidEscape => "expected one of \"\\ubfnrt\"",
             U::InvalidEscapeHex => "expected hexidecimal",
+            U::InvalidLiteral => "expected true, false or null",
             U::Character => "unexpected character",
+            U::LineEnd => "unexpected end of line",
+            U::Truncated => "truncated record",
             U::Eof => "unexpected EOF",
         }
     }
 }
+
+/// A token the parser would have accepted at some point in the input.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Expected {
+    Value,
+    Key,
+    Colon,
+    Comma,
+    ObjectClose,
+    ArrayClose,
+    Digit,
+    Dot,
+    Exponent,
+    Sign,
+    Quote,
+    Escape,
+    HexDigit,
+    Byte(u8),
+    Eof,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Expected::Value => f.write_str("value"),
+            Expected::Key => f.write_str("object key"),
+            Expected::Colon => f.write_str("':'"),
+            Expected::Comma => f.write_str("','"),
+            Expected::ObjectClose => f.write_str("'}'"),
+            Expected::ArrayClose => f.write_str("']'"),
+            Expected::Digit => f.write_str("digit"),
+            Expected::Dot => f.write_str("'.'"),
+            Expected::Exponent => f.write_str("'e'"),
+            Expected::Sign => f.write_str("'-' or '+'"),
+            Expected::Quote => f.write_str("'\"'"),
+            Expected::Escape => f.write_str("one of \"\\/bfnrtu"),
+            Expected::HexDigit => f.write_str("hexadecimal digit"),
+            Expected::Byte(byte) => write!(f, "'{}'", byte as char),
+            Expected::Eof => f.write_str("end of input"),
+        }
+    }
+}
+
+/// What the parser expected to see next, see `TokenizerState::expected`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Expectation {
+    pub tokens: Vec<Expected>,
+    /// Where in the document the parser is, e.g. "after object value".
+    pub context: Option<&'static str>,
+}
+
+impl Expectation {
+    pub fn new(tokens: &[Expected], context: &'static str) -> Expectation {
+        Expectation {
+            tokens: tokens.to_vec(),
+            context: Some(context),
+        }
+    }
+}
+
+impl fmt::Display for Unexpected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.explain())
+    }
+}
+
+/// Error returned from the high level `Decoder` API.
+#[derive(Debug)]
+pub enum Error<SinkBail = ()> {
+    Unexpected(Pos, Unexpected),
+    Io(io::Error),
+    // The sink bailed, aborting the parse.
+    Sink(SinkBail),
+}
+
+impl<SinkBail> From<io::Error> for Error<SinkBail> {
+    fn from(err: io::Error) -> Error<SinkBail> {
+        Error::Io(err)
+    }
+}
+
+impl<SinkBail> fmt::Display for Error<SinkBail> where SinkBail: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Unexpected(pos, reason) => write!(f, "{} at byte {}", reason, pos.0),
+            Error::Io(ref err) => write!(f, "io error: {}", err),
+            Error::Sink(ref bail) => write!(f, "aborted by sink: {:?}", bail),
+        }
+    }
+}
+
+impl<SinkBail> error::Error for Error<SinkBail> where SinkBail: fmt::Debug {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use ::parser::NumberData;
 use ::source::{Source, PeekResult};
-use ::sink::{Sink, Position, StringPosition};
+use ::sink::{Sink, Position, StringPosition, StringKind};
+use ::error::Unexpected;
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct Pos(pub usize);
@@ -34,6 +35,41 @@ impl Range {
     }
 }
 
+/// The bytes a `Source` currently holds in memory, starting at the absolute
+/// position `start`.
+///
+/// Every `Range` handed to a `Sink` can be resolved against the window of the
+/// source at the time of the event.
+#[derive(Debug, Copy, Clone)]
+pub struct Window<'a> {
+    pub start: Pos,
+    pub bytes: &'a [u8],
+}
+
+impl<'a> Window<'a> {
+    pub fn new(start: Pos, bytes: &'a [u8]) -> Window<'a> {
+        Window {
+            start,
+            bytes,
+        }
+    }
+
+    /// Returns the bytes covered by `range`, or `None` if they are not
+    /// (or no longer) held by the source.
+    pub fn get(&self, range: Range) -> Option<&'a [u8]> {
+        if range.start < self.start.0 || range.end < range.start {
+            return None;
+        }
+        self.bytes.get((range.start - self.start.0)..(range.end - self.start.0))
+    }
+}
+
+impl<'a> From<&'a [u8]> for Window<'a> {
+    fn from(bytes: &'a [u8]) -> Window<'a> {
+        Window::new(0.into(), bytes)
+    }
+}
+
 pub trait Bailable {
     type Bail;
 }
@@ -44,6 +80,7 @@ pub enum BailVariant<SourceBail, SinkBail> {
     Sink(SinkBail),
 }
 
+#[derive(Debug)]
 pub struct SourceSink<Src, Snk>
     where Src: Source,
           Snk: Sink
@@ -85,6 +122,10 @@ impl<Src, Snk> Source for SourceSink<Src, Snk>
     fn peek_slice<'a>(&'a self, length: usize) -> Option<&'a [u8]> {
         self.source.peek_slice(length)
     }
+    #[inline(always)]
+    fn window(&self) -> Window<'_> {
+        self.source.window()
+    }
 }
 
 macro_rules! lift_bail {
@@ -111,7 +152,7 @@ impl<Src, Snk> Sink for SourceSink<Src, Snk>
     }
     #[inline(always)]
     fn push_number(&mut self, pos: Position, integer: NumberData) -> Result<(), Self::Bail> {
-        lift_bail!(self.sink.push_number(pos, integer))
+        lift_bail!(self.sink.push_number_window(pos, integer, self.source.window()))
     }
     #[inline(always)]
     fn push_bool(&mut self, pos: Position, boolean: bool) -> Result<(), Self::Bail> {
@@ -127,8 +168,12 @@ impl<Src, Snk> Sink for SourceSink<Src, Snk>
         self.sink.start_string(pos)
     }
     #[inline(always)]
+    fn start_string_kind(&mut self, pos: StringPosition, kind: StringKind) {
+        self.sink.start_string_kind(pos, kind)
+    }
+    #[inline(always)]
     fn append_string_range(&mut self, string: Range) {
-        self.sink.append_string_range(string)
+        self.sink.append_string_window(string, self.source.window())
     }
     #[inline(always)]
     fn append_string_single(&mut self, character: u8) {
@@ -159,4 +204,24 @@ impl<Src, Snk> Sink for SourceSink<Src, Snk>
     fn pop_into_map(&mut self) {
         self.sink.pop_into_map()
     }
+    #[inline(always)]
+    fn comment(&mut self, comment: Range) {
+        self.sink.comment_window(comment, self.source.window())
+    }
+    #[inline(always)]
+    fn skip_value(&mut self, pos: Position) -> bool {
+        self.sink.skip_value(pos)
+    }
+    #[inline(always)]
+    fn start_document(&mut self, start: Pos) {
+        self.sink.start_document(start)
+    }
+    #[inline(always)]
+    fn end_document(&mut self, document: Range) -> Result<(), Self::Bail> {
+        lift_bail!(self.sink.end_document(document))
+    }
+    #[inline(always)]
+    fn document_error(&mut self, document: Range, pos: Pos, reason: Unexpected) -> Result<(), Self::Bail> {
+        lift_bail!(self.sink.document_error(document, pos, reason))
+    }
 }
@@ -9,16 +9,24 @@ pub mod input;
 pub mod error;
 pub mod source;
 pub mod decoder;
+pub mod options;
+pub mod report;
+pub mod push;
+pub mod pull;
+pub mod number;
 mod utf8;
 
-pub use error::{ParseError, Unexpected};
+pub use error::{ParseError, Unexpected, Error, Expected, Expectation};
 
-pub use input::{Range, Pos};
+pub use input::{Range, Pos, Window};
 pub use source::{Source, PeekResult};
-pub use sink::{Sink, Position, StringPosition};
+pub use sink::{Sink, Position, StringPosition, StringKind};
 
-pub use parser::NumberData;
+pub use parser::{NumberData, NumberKind};
+pub use number::{NumberError, Decimal};
 pub use tokenizer::{TokenizerState};
+pub use decoder::Decoder;
+pub use options::{Options, Limits, Documents, Dialect};
 pub use TokenizerState as Parser;
 
 pub use input::{Bailable, SourceSink, BailVariant};
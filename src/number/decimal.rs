@@ -0,0 +1,166 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use ::input::Window;
+use ::parser::NumberData;
+use super::NumberError;
+
+/// A decimal number stored without loss of precision, as the value
+/// `digits * 10^-scale`.
+///
+/// A decimal keeps the digits it was written with, so `1.50` remembers its
+/// trailing zero until normalized. Comparisons are by value: `1.50` equals
+/// `1.5`, and `-0` equals `0`.
+#[derive(Debug, Clone)]
+pub struct Decimal {
+    negative: bool,
+    // ASCII digits without leading zeros. Empty for zero.
+    digits: Vec<u8>,
+    scale: i64,
+}
+
+impl Decimal {
+    /// Whether the number was written with a minus sign. Can be true for
+    /// zero.
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// The significant digits as ASCII, without leading zeros. Empty for
+    /// zero.
+    pub fn digits(&self) -> &[u8] {
+        &self.digits
+    }
+
+    /// The number of digits after the decimal point. Negative if the digits
+    /// are to be followed by zeros.
+    pub fn scale(&self) -> i64 {
+        self.scale
+    }
+
+    /// Removes trailing zeros from the digits, and the sign from zero.
+    /// Decimals equal in value are identical after normalization.
+    pub fn normalize(&mut self) {
+        let zeros = self.digits.iter().rev().take_while(|&&digit| digit == b'0').count();
+        // Keep zeros the scale can not account for.
+        let zeros = ::std::cmp::min(zeros as i128, self.scale as i128 - i64::MIN as i128) as usize;
+        let length = self.digits.len() - zeros;
+        self.digits.truncate(length);
+        self.scale -= zeros as i64;
+        if self.digits.is_empty() {
+            self.negative = false;
+            self.scale = 0;
+        }
+    }
+
+    pub fn normalized(mut self) -> Decimal {
+        self.normalize();
+        self
+    }
+
+    // Power of ten of the leading digit, as in scientific notation.
+    fn adjusted_exponent(&self) -> i128 {
+        self.digits.len() as i128 - 1 - self.scale as i128
+    }
+
+    fn cmp_magnitude(&self, other: &Decimal) -> Ordering {
+        match (self.is_zero(), other.is_zero()) {
+            (true, true) => return Ordering::Equal,
+            (true, false) => return Ordering::Less,
+            (false, true) => return Ordering::Greater,
+            (false, false) => (),
+        }
+        self.adjusted_exponent().cmp(&other.adjusted_exponent()).then_with(|| {
+            // Same leading power, so the digits line up. The shorter one is
+            // padded with zeros.
+            let length = ::std::cmp::max(self.digits.len(), other.digits.len());
+            let padded = |digits: &[u8], i: usize| digits.get(i).cloned().unwrap_or(b'0');
+            (0..length)
+                .map(|i| padded(&self.digits, i).cmp(&padded(&other.digits, i)))
+                .find(|&ordering| ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        })
+    }
+}
+
+impl NumberData {
+    /// The exact value of the number. Fails with `OutOfRange` only if the
+    /// power of ten does not fit in an `i64`, and with `NonFinite` for
+    /// `Infinity` and `NaN`.
+    pub fn to_decimal(&self, window: Window<'_>) -> Result<Decimal, NumberError> {
+        let (digits, power) = self.significand(window)?;
+        Ok(Decimal {
+            negative: !self.sign,
+            digits,
+            scale: power.checked_neg().ok_or(NumberError::OutOfRange)?,
+        })
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        let negative = |decimal: &Decimal| decimal.negative && !decimal.is_zero();
+        match (negative(self), negative(other)) {
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+/// Formats the decimal as a JSON number, keeping all digits. Plain notation
+/// is used unless the digits would have to be followed by zeros, or preceded
+/// by more than five zeros after the decimal point, in which case the
+/// exponent is written out: `12.50`, `0.000001`, `1.2e+3`, `1e-7`.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.negative {
+            f.write_str("-")?;
+        }
+        let digits = if self.is_zero() { &b"0"[..] } else { &self.digits[..] };
+        let digits = ::std::str::from_utf8(digits).unwrap();
+        let adjusted = digits.len() as i128 - 1 - self.scale as i128;
+
+        if self.scale >= 0 && adjusted >= -6 {
+            let scale = self.scale as usize;
+            if scale == 0 {
+                f.write_str(digits)
+            } else if digits.len() > scale {
+                let (integer, fraction) = digits.split_at(digits.len() - scale);
+                write!(f, "{}.{}", integer, fraction)
+            } else {
+                f.write_str("0.")?;
+                for _ in digits.len()..scale {
+                    f.write_str("0")?;
+                }
+                f.write_str(digits)
+            }
+        } else {
+            let (first, rest) = digits.split_at(1);
+            f.write_str(first)?;
+            if !rest.is_empty() {
+                write!(f, ".{}", rest)?;
+            }
+            write!(f, "e{}{}", if adjusted < 0 { "-" } else { "+" }, adjusted.abs())
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Conversions of `NumberData` into Rust numbers.
+//!
+//! `NumberData` only holds the ranges of a number in the input, so every
+//! conversion takes the `Window` the ranges can be resolved against. This is
+//! the window handed to `Sink::push_number_window`, or the whole input for
+//! sources keeping everything in memory.
+//!
+//! Integer conversions are exact. A number converts to an integer type if
+//! its value is an integer in range, whatever notation it is written in:
+//! `1.5e1` is `15`, and `-0.0` is `0`. For values that must not be rounded
+//! at all, see `Decimal`.
+//!
+//! JSON5 hexadecimal numbers convert like any other integer. `Infinity` and
+//! `NaN` only convert to `f64`.
+
+use std::fmt;
+use std::error;
+use std::convert::TryFrom;
+
+use ::input::{Range, Window};
+use ::parser::{NumberData, NumberKind};
+
+mod decimal;
+
+pub use self::decimal::Decimal;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NumberError {
+    /// The value has a fractional part, and the target type is an integer.
+    Fractional,
+    /// The value is too large or too small for the target type.
+    OutOfRange,
+    /// A range of the number is not in the window.
+    Unavailable,
+    /// The number is `Infinity` or `NaN`, and the target type has no such
+    /// values.
+    NonFinite,
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match *self {
+            NumberError::Fractional => "number is not an integer",
+            NumberError::OutOfRange => "number out of range",
+            NumberError::Unavailable => "number is not in the window",
+            NumberError::NonFinite => "number is not finite",
+        };
+        f.write_str(description)
+    }
+}
+
+impl error::Error for NumberError {}
+
+// The digits of an optional part of a number, empty if it is missing.
+fn digits<'a>(window: Window<'a>, range: Option<Range>) -> Result<&'a [u8], NumberError> {
+    match range {
+        None => Ok(&[]),
+        Some(range) => window.get(range).ok_or(NumberError::Unavailable),
+    }
+}
+
+// Converts hexadecimal digits to decimal ones, without leading zeros.
+fn hex_to_decimal(hex: &[u8]) -> Vec<u8> {
+    // Decimal digits as values, least significant first.
+    let mut digits: Vec<u8> = Vec::new();
+    for &character in hex {
+        let mut carry = (character as char).to_digit(16).unwrap();
+        for digit in digits.iter_mut() {
+            let value = *digit as u32 * 16 + carry;
+            *digit = (value % 10) as u8;
+            carry = value / 10;
+        }
+        while carry > 0 {
+            digits.push((carry % 10) as u8);
+            carry /= 10;
+        }
+    }
+    digits.iter().rev().map(|&digit| digit + b'0').collect()
+}
+
+impl NumberData {
+    /// The value as the nearest `f64`, rounding ties to even. Fails with
+    /// `OutOfRange` if the value rounds to infinity. Values too small to
+    /// represent round to zero. `Infinity` and `NaN` convert to the
+    /// corresponding values.
+    pub fn to_f64(&self, window: Window<'_>) -> Result<f64, NumberError> {
+        let sign = if self.sign { 1.0 } else { -1.0 };
+        match self.kind {
+            NumberKind::Infinity => return Ok(sign * f64::INFINITY),
+            NumberKind::NaN => return Ok(sign * f64::NAN),
+            NumberKind::Hexadecimal => {
+                let (significand, _) = self.significand(window)?;
+                let text: String = significand.iter().map(|&b| b as char).collect();
+                let value = if text.is_empty() { 0.0 } else { text.parse::<f64>().unwrap() };
+                if value.is_infinite() {
+                    return Err(NumberError::OutOfRange);
+                }
+                return Ok(sign * value);
+            }
+            NumberKind::Decimal => (),
+        }
+
+        let integer = digits(window, Some(self.integer))?;
+        let decimal = digits(window, self.decimal)?;
+        let exponent = digits(window, self.exponent)?;
+
+        // The parser has already validated the number, so the tokens can be
+        // handed to the standard library, which rounds correctly.
+        let mut text = String::with_capacity(integer.len() + decimal.len() + exponent.len() + 4);
+        if !self.sign {
+            text.push('-');
+        }
+        // JSON5 allows numbers to start with their decimal point.
+        if integer.is_empty() {
+            text.push('0');
+        }
+        text.extend(integer.iter().map(|&b| b as char));
+        if !decimal.is_empty() {
+            text.push('.');
+            text.extend(decimal.iter().map(|&b| b as char));
+        }
+        if !exponent.is_empty() {
+            text.push('e');
+            if !self.exponent_sign {
+                text.push('-');
+            }
+            text.extend(exponent.iter().map(|&b| b as char));
+        }
+
+        let value: f64 = text.parse().unwrap();
+        if value.is_infinite() {
+            return Err(NumberError::OutOfRange);
+        }
+        Ok(value)
+    }
+
+    pub fn to_i64(&self, window: Window<'_>) -> Result<i64, NumberError> {
+        let value = self.to_i128(window)?;
+        if value < i64::MIN as i128 || value > i64::MAX as i128 {
+            return Err(NumberError::OutOfRange);
+        }
+        Ok(value as i64)
+    }
+
+    pub fn to_u64(&self, window: Window<'_>) -> Result<u64, NumberError> {
+        let (negative, magnitude) = self.integer_magnitude(window)?;
+        if (negative && magnitude != 0) || magnitude > u64::MAX as u128 {
+            return Err(NumberError::OutOfRange);
+        }
+        Ok(magnitude as u64)
+    }
+
+    pub fn to_i128(&self, window: Window<'_>) -> Result<i128, NumberError> {
+        let (negative, magnitude) = self.integer_magnitude(window)?;
+        if negative {
+            if magnitude > i128::MAX as u128 + 1 {
+                return Err(NumberError::OutOfRange);
+            }
+            Ok((magnitude as i128).wrapping_neg())
+        } else {
+            if magnitude > i128::MAX as u128 {
+                return Err(NumberError::OutOfRange);
+            }
+            Ok(magnitude as i128)
+        }
+    }
+
+    /// Whether the number is written without a fraction or an exponent.
+    /// Numbers written with them may still convert to integers.
+    /// Hexadecimal numbers are integer literals, `Infinity` and `NaN` are
+    /// not.
+    pub fn is_integer_literal(&self) -> bool {
+        match self.kind {
+            NumberKind::Decimal => self.decimal.is_none() && self.exponent.is_none(),
+            NumberKind::Hexadecimal => true,
+            NumberKind::Infinity | NumberKind::NaN => false,
+        }
+    }
+
+    // The digits of the integer and fraction with leading zeros removed,
+    // and the power of ten to multiply them by. Fails with `OutOfRange` if
+    // the power does not fit in an `i64`.
+    fn significand(&self, window: Window<'_>) -> Result<(Vec<u8>, i64), NumberError> {
+        match self.kind {
+            NumberKind::Decimal => (),
+            NumberKind::Hexadecimal => {
+                let integer = digits(window, Some(self.integer))?;
+                return Ok((hex_to_decimal(integer), 0));
+            }
+            NumberKind::Infinity | NumberKind::NaN => return Err(NumberError::NonFinite),
+        }
+
+        let integer = digits(window, Some(self.integer))?;
+        let decimal = digits(window, self.decimal)?;
+        let exponent = digits(window, self.exponent)?;
+
+        let significand = integer.iter().chain(decimal.iter())
+            .cloned()
+            .skip_while(|&digit| digit == b'0')
+            .collect();
+
+        let mut power: i64 = 0;
+        for &digit in exponent {
+            power = power.checked_mul(10)
+                .and_then(|p| p.checked_add((digit - b'0') as i64))
+                .ok_or(NumberError::OutOfRange)?;
+        }
+        let power = if self.exponent_sign { power } else { -power };
+        let power = power.checked_sub(decimal.len() as i64).ok_or(NumberError::OutOfRange)?;
+        Ok((significand, power))
+    }
+
+    // The absolute value of the number if it is an integer, and whether the
+    // number is negative.
+    fn integer_magnitude(&self, window: Window<'_>) -> Result<(bool, u128), NumberError> {
+        // The value is `significand * 10^scale`.
+        let (significand, scale) = match self.significand(window) {
+            Ok(parts) => parts,
+            // The power does not fit, so unless the number is zero it is too
+            // large, or has a fraction.
+            Err(NumberError::OutOfRange) => {
+                let integer = digits(window, Some(self.integer))?;
+                let decimal = digits(window, self.decimal)?;
+                if integer.iter().chain(decimal.iter()).all(|&digit| digit == b'0') {
+                    return Ok((!self.sign, 0));
+                }
+                return Err(if self.exponent_sign { NumberError::OutOfRange } else { NumberError::Fractional });
+            }
+            Err(error) => return Err(error),
+        };
+        if significand.is_empty() {
+            return Ok((!self.sign, 0));
+        }
+
+        let kept = if scale < 0 {
+            let dropped = scale.unsigned_abs().min(significand.len() as u64) as usize;
+            let (kept, dropped) = significand.split_at(significand.len() - dropped);
+            if dropped.iter().any(|&digit| digit != b'0') {
+                return Err(NumberError::Fractional);
+            }
+            kept
+        } else {
+            &significand[..]
+        };
+
+        let mut magnitude: u128 = 0;
+        for &digit in kept {
+            magnitude = magnitude.checked_mul(10)
+                .and_then(|m| m.checked_add((digit - b'0') as u128))
+                .ok_or(NumberError::OutOfRange)?;
+        }
+        if scale > 0 {
+            let power = u32::try_from(scale).ok()
+                .and_then(|scale| 10u128.checked_pow(scale))
+                .ok_or(NumberError::OutOfRange)?;
+            magnitude = magnitude.checked_mul(power).ok_or(NumberError::OutOfRange)?;
+        }
+
+        Ok((!self.sign, magnitude))
+    }
+}
@@ -0,0 +1,112 @@
+/// Options controlling what input the tokenizer and parser accept.
+///
+/// ```ignore
+/// let tokenizer = TokenizerState::with_options(Options {
+///     strict: true,
+///     ..Options::default()
+/// });
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Options {
+    /// Reject everything not allowed by RFC 8259. By default a few harmless
+    /// extensions are accepted: trailing commas in objects and arrays, a
+    /// leading `+` on numbers and leading zeros in numbers.
+    pub strict: bool,
+    /// Guardrails for untrusted input.
+    pub limits: Limits,
+    /// Whether the input holds one document or several.
+    pub documents: Documents,
+    /// The language of the input.
+    pub dialect: Dialect,
+    /// Allow `//` line comments and `/* */` block comments wherever
+    /// whitespace is, as in JSON with comments. Always allowed in JSON5.
+    /// Also allowed in strict mode.
+    pub comments: bool,
+    /// Accept the numbers `NaN`, `Infinity` and `-Infinity`, as written by
+    /// Python's `json` module. They are passed on with their own
+    /// `NumberKind`. Always allowed in JSON5. Also allowed in strict mode.
+    pub non_finite: bool,
+}
+
+/// The language of the input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Dialect {
+    #[default]
+    Json,
+    /// JSON5, see <https://spec.json5.org>. On top of JSON this accepts
+    /// unquoted keys, single quoted strings, hexadecimal numbers, leading
+    /// and trailing decimal points, `Infinity` and `NaN`, leading `+`
+    /// signs, trailing commas, comments, and the escapes of JavaScript
+    /// strings, including line continuations. All of these are accepted in
+    /// strict mode too, which then only rejects leading zeros.
+    ///
+    /// The whitespace characters JSON5 adds to JSON are not accepted.
+    /// Unquoted keys can not contain escapes, and may contain any non-ASCII
+    /// character.
+    Json5,
+}
+
+/// How many documents the input holds, and how they are separated.
+///
+/// With several documents, the sink is told where each one starts and
+/// ends, and `run` only returns `Ok` at the end of the input. Root values
+/// can be scalars. A document that fails to parse is reported through
+/// `Sink::document_error`, and parsing continues with the next one, unless
+/// documents are only concatenated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Documents {
+    #[default]
+    Single,
+    /// One document per line, as in NDJSON and JSON Lines. Blank lines are
+    /// skipped. A document can not span lines, and a failed one is skipped
+    /// up to the end of its line.
+    Lines,
+    /// JSON text sequences, as in RFC 7464, where each document follows a
+    /// record separator byte (`0x1E`). A failed document is skipped up to
+    /// the next record separator. A document cut off by a record separator
+    /// fails with `Unexpected::Truncated`, as does a root number, `true`,
+    /// `false` or `null` not followed by whitespace, since it may have been
+    /// cut off.
+    Sequence,
+    /// Documents one after the other, like `{}{}[1]"x"`, separated by
+    /// optional whitespace. Root numbers need whitespace or another value
+    /// after them. Parsing stops at the first error, since there is no way
+    /// to find the start of the next document.
+    Concatenated,
+}
+
+/// Limits on the size of a document and the values in it. Each limit is
+/// unlimited if `None`, and exceeding it fails with its own `Unexpected`
+/// variant.
+///
+/// Bailing sources may report a string, number or document limit as soon
+/// as they run out of data, before the token in question has ended.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// Maximum number of nested objects and arrays. Reported at the bracket
+    /// opening one too many as `Unexpected::DepthLimit`.
+    pub max_depth: Option<usize>,
+    /// Maximum length of a string in bytes, after unescaping. Reported at the
+    /// opening quote as `Unexpected::StringLimit`.
+    pub max_string_length: Option<usize>,
+    /// Maximum number of digits in the integer, fraction or exponent part of
+    /// a number. Reported at the first digit of the part as
+    /// `Unexpected::NumberLimit`.
+    pub max_number_length: Option<usize>,
+    /// Maximum number of values in an array, or keys in an object. Reported
+    /// at the first member too many as `Unexpected::MemberLimit`.
+    pub max_members: Option<usize>,
+    /// Maximum number of bytes consumed from the source, counted per
+    /// document if there are several. Reported at the limit as
+    /// `Unexpected::DocumentLimit`.
+    pub max_document_size: Option<usize>,
+}
+
+impl Options {
+    pub fn strict() -> Options {
+        Options {
+            strict: true,
+            ..Options::default()
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use ::PResult;
 use ::Bailable;
-use ::sink::{Sink, Position, StringPosition};
-use ::error::{ParseError, Unexpected};
-use ::input::Range;
+use ::sink::{Sink, Position, StringPosition, StringKind};
+use ::error::{ParseError, Unexpected, Expected, Expectation};
+use ::input::{Pos, Range};
 use ::source::Source;
+use ::options::{Options, Documents, Dialect};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum NumberState {
@@ -13,12 +14,31 @@ enum NumberState {
     ExponentStartEnd, // 'eE' or end
     ExponentSign, // '-+' or Exponent
     Exponent, // Exponent then end
+    End, // end
+}
+
+/// How a number is written. Only `Decimal` numbers are valid JSON, the
+/// others are JSON5. `Infinity` and `NaN` are also accepted with
+/// `Options::non_finite`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NumberKind {
+    Decimal,
+    /// `0x` followed by the hexadecimal digits in `integer`.
+    Hexadecimal,
+    /// `Infinity`, with an empty `integer`.
+    Infinity,
+    /// `NaN`, with an empty `integer`.
+    NaN,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NumberData {
+    pub kind: NumberKind,
     pub sign: bool,
+    /// Empty if the number starts with its decimal point, which JSON5
+    /// allows.
     pub integer: Range,
+    /// Empty if the number ends with its decimal point, which JSON5 allows.
     pub decimal: Option<Range>,
     pub exponent_sign: bool,
     pub exponent: Option<Range>,
@@ -26,6 +46,7 @@ pub struct NumberData {
 impl Default for NumberData {
     fn default() -> Self {
         NumberData {
+            kind: NumberKind::Decimal,
             sign: true,
             integer: Range::new(0.into(), 0.into()),
             decimal: None,
@@ -92,20 +113,49 @@ impl TopStateContext {
 
 #[derive(Debug)]
 pub struct ParserState {
+    options: Options,
     stack: Vec<StackState>,
+    // Number of members read so far in each container on the stack.
+    members: Vec<usize>,
 
     state: TopState,
     read_value: bool,
     reentry_action: ReentryAction,
     started: bool,
+    // Set when the last token was a comma, cleared when a container is
+    // opened. Used to detect trailing commas.
+    after_comma: bool,
 
     number_state: NumberState,
     number_data: NumberData,
+    // Position right after the last token of the number being read. The
+    // tokens making up a number can not be separated by whitespace.
+    number_end: usize,
+
+    // Position of the opening quote, and the unescaped length so far, of
+    // the string being read.
+    string_start: usize,
+    string_length: usize,
+
+    // Set when the sink asked to skip the value that was just started, until
+    // the tokenizer has scanned past it.
+    skipping: bool,
+    // Set when the last value was skipped, so that it is not popped into its
+    // container.
+    value_skipped: bool,
 }
 
 macro_rules! unexpected {
     ($ss:expr, $reason:expr) => { Err(ParseError::Unexpected($ss.position(), $reason)) }
 }
+// For single byte tokens, which have already been consumed from the source
+// when the parser sees them. Reports the position of the token itself.
+macro_rules! unexpected_token {
+    ($ss:expr, $reason:expr) => { Err(ParseError::Unexpected(Pos($ss.position().0 - 1), $reason)) }
+}
+macro_rules! unexpected_at {
+    ($pos:expr, $reason:expr) => { Err(ParseError::Unexpected($pos, $reason)) }
+}
 
 macro_rules! lift_bail {
     ($bailing:expr) => {
@@ -141,16 +191,121 @@ fn log_token(token: &str) {
 impl ParserState {
 
     pub fn new() -> Self {
+        ParserState::with_options(Options::default())
+    }
+
+    pub fn with_options(options: Options) -> Self {
         ParserState {
+            options,
             stack: vec![],
+            members: vec![],
 
             state: TopState::None,
-            read_value: false,
+            // Scalars are only allowed as root values when the input after
+            // the root value is read, since a root number may only end
+            // there. That is the case when documents are framed, or in
+            // strict mode, where only whitespace may follow.
+            read_value: options.documents != Documents::Single || options.strict,
             reentry_action: ReentryAction::None,
             started: false,
+            after_comma: false,
 
             number_state: NumberState::Integer,
             number_data: NumberData::default(),
+            number_end: 0,
+
+            string_start: 0,
+            string_length: 0,
+
+            skipping: false,
+            value_skipped: false,
+        }
+    }
+
+    fn json5(&self) -> bool {
+        self.options.dialect == Dialect::Json5
+    }
+
+    // Whether extensions to JSON that JSON5 allows, like trailing commas,
+    // are rejected.
+    fn rejects_extensions(&self) -> bool {
+        self.options.strict && !self.json5()
+    }
+
+    // Starts a number that does not start with a digit or sign. The caller
+    // sets up the rest of the number state.
+    fn start_number<B>(&mut self, at: Pos) -> PResult<(), B> {
+        if !self.read_value {
+            return unexpected_at!(at, Unexpected::Number);
+        }
+        if !self.count_member() {
+            return unexpected_at!(at, Unexpected::MemberLimit);
+        }
+        self.read_value = false;
+        self.started = true;
+        self.number_data = NumberData::default();
+        self.state = TopState::Number(TopStateContext::from_topstate(self.state));
+        Ok(())
+    }
+
+    // Called when the number ends right after its decimal point. JSON5
+    // allows that if there are digits before it, which this checks. The
+    // fraction is then recorded as empty.
+    fn end_fraction(&mut self) -> bool {
+        if !self.json5() || self.number_data.integer.size() == 0 {
+            return false;
+        }
+        let end = Pos(self.number_end);
+        self.number_data.decimal = Some(Range::new(end, end));
+        true
+    }
+
+    fn continues_number(&self, start: usize) -> bool {
+        matches!(self.state, TopState::Number(_)) && start == self.number_end
+    }
+
+    fn at_depth_limit(&self) -> bool {
+        self.options.limits.max_depth.is_some_and(|max| self.stack.len() >= max)
+    }
+
+    // Counts the value about to be read if it is a member of an array, or a
+    // key of an object. Returns false if that goes over the limit.
+    fn count_member(&mut self) -> bool {
+        match self.state {
+            TopState::ArrayCommaEnd | TopState::ObjectKeyEnd => (),
+            _ => return true,
+        }
+        let count = self.members.last_mut().unwrap();
+        *count += 1;
+        self.options.limits.max_members.is_none_or(|max| *count <= max)
+    }
+
+    fn exceeds_string_limit(&self, length: usize) -> bool {
+        self.options.limits.max_string_length.is_some_and(|max| length > max)
+    }
+
+    // Asks the sink whether to skip the value starting here. If so, the
+    // parser waits for `finish_skip`.
+    fn start_skip<SS>(&mut self, ss: &mut SS) -> bool where SS: Source + Sink + Bailable {
+        if !ss.skip_value(self.get_position()) {
+            return false;
+        }
+        self.read_value = false;
+        self.started = true;
+        self.after_comma = false;
+        self.skipping = true;
+        true
+    }
+
+    fn pop_into_map<SS>(&mut self, ss: &mut SS) where SS: Source + Sink + Bailable {
+        if !::std::mem::replace(&mut self.value_skipped, false) {
+            ss.pop_into_map();
+        }
+    }
+
+    fn pop_into_array<SS>(&mut self, ss: &mut SS) where SS: Source + Sink + Bailable {
+        if !::std::mem::replace(&mut self.value_skipped, false) {
+            ss.pop_into_array();
         }
     }
 
@@ -162,11 +317,18 @@ impl ParserState {
         }
     }
 
-    fn handle_end_number<SS>(&mut self, ss: &mut SS, position: Position, next: TopStateContext, unexpected: Unexpected) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
-        if self.number_state != NumberState::ExponentStartEnd
-            && self.number_state != NumberState::DotExponentEnd {
-                return unexpected!(ss, unexpected);
-            }
+    // `at` is the position of whatever ended the number.
+    fn handle_end_number<SS>(&mut self, ss: &mut SS, position: Position, next: TopStateContext, at: Pos, unexpected: Unexpected) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
+        if self.number_state == NumberState::Decimal && self.end_fraction() {
+            self.number_state = NumberState::End;
+        }
+        match self.number_state {
+            NumberState::ExponentStartEnd | NumberState::DotExponentEnd | NumberState::End => (),
+            NumberState::Decimal => return unexpected_at!(at, Unexpected::EmptyFraction),
+            NumberState::ExponentSign | NumberState::Exponent =>
+                return unexpected_at!(at, Unexpected::EmptyExponent),
+            NumberState::Integer => return unexpected_at!(at, unexpected),
+        }
 
         self.state = match next {
             TopStateContext::None => {
@@ -188,13 +350,24 @@ impl ParserState {
         log_token("object_open");
 
         if !self.read_value && self.state != TopState::None {
-            return unexpected!(ss, Unexpected::ObjectOpen);
+            return unexpected_token!(ss, Unexpected::ObjectOpen);
+        }
+        if self.at_depth_limit() {
+            return unexpected_token!(ss, Unexpected::DepthLimit);
+        }
+        if !self.count_member() {
+            return unexpected_token!(ss, Unexpected::MemberLimit);
+        }
+        if self.start_skip(ss) {
+            return Ok(());
         }
         self.read_value = false;
         self.started = true;
+        self.after_comma = false;
 
         ss.push_map(self.get_position());
         self.stack.push(StackState::Object);
+        self.members.push(0);
         self.state = TopState::ObjectKeyEnd;
         Ok(())
     }
@@ -203,13 +376,24 @@ impl ParserState {
         log_token("array_open");
 
         if !self.read_value && self.state != TopState::None {
-            return unexpected!(ss, Unexpected::ArrayOpen);
+            return unexpected_token!(ss, Unexpected::ArrayOpen);
+        }
+        if self.at_depth_limit() {
+            return unexpected_token!(ss, Unexpected::DepthLimit);
+        }
+        if !self.count_member() {
+            return unexpected_token!(ss, Unexpected::MemberLimit);
+        }
+        if self.start_skip(ss) {
+            return Ok(());
         }
         self.read_value = true;
         self.started = true;
+        self.after_comma = false;
 
         ss.push_array(self.get_position());
         self.stack.push(StackState::Array);
+        self.members.push(0);
         self.state = TopState::ArrayCommaEnd;
         Ok(())
     }
@@ -221,10 +405,13 @@ impl ParserState {
             // An object end can only occur if we are waiting for a comma or waiting
             // for a key. We are diverging a bit from the spec here, and are allowing
             // trailing commas. This makes the state machine a bit simpler, and I like
-            // trailing commas,
+            // trailing commas. Strict mode does not.
+            TopState::ObjectKeyEnd if self.rejects_extensions() && self.after_comma => {
+                return unexpected_token!(ss, Unexpected::TrailingComma);
+            },
             TopState::ObjectKeyEnd | TopState::ObjectCommaEnd | TopState::Number(TopStateContext::ObjectValue) => {
                 if let TopState::Number(context) = self.state {
-                    match self.handle_end_number(ss, Position::MapValue, context, Unexpected::ObjectClose) {
+                    match self.handle_end_number(ss, Position::MapValue, context, Pos(ss.position().0 - 1), Unexpected::ObjectClose) {
                         Ok(()) => (),
                         Err(err) => {
                             self.reentry_action = ReentryAction::FinishObjectClose;
@@ -235,7 +422,7 @@ impl ParserState {
 
                 self.finish_object_close(ss)?;
             },
-            _ => return unexpected!(ss, Unexpected::ObjectClose),
+            _ => return unexpected_token!(ss, Unexpected::ObjectClose),
         }
 
         Ok(())
@@ -243,13 +430,13 @@ impl ParserState {
 
     pub fn finish_object_close<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
         if self.read_value && self.state == TopState::ObjectCommaEnd {
-            return unexpected!(ss, Unexpected::ObjectClose);
+            return unexpected_token!(ss, Unexpected::ObjectClose);
         }
 
         // If the read_value flag is not set, it means we just read in a value
         // and need to pop_into_map.
         if !self.read_value && self.state == TopState::ObjectCommaEnd {
-            ss.pop_into_map();
+            self.pop_into_map(ss);
         }
 
         self.read_value = false;
@@ -259,6 +446,7 @@ impl ParserState {
         // sure that there is a value for us to pop, and that it, in fact, is
         // a StackState::Object.
         self.stack.pop().unwrap();
+        self.members.pop();
 
         // Look at the last value on the stack to determine what our next state
         // should be.
@@ -280,9 +468,12 @@ impl ParserState {
         log_token("array_close");
 
         match self.state {
+            TopState::ArrayCommaEnd if self.rejects_extensions() && self.read_value && self.after_comma => {
+                return unexpected_token!(ss, Unexpected::TrailingComma);
+            },
             TopState::ArrayCommaEnd | TopState::Number(TopStateContext::ArrayValue) => {
                 if let TopState::Number(context) = self.state {
-                    match self.handle_end_number(ss, Position::ArrayValue, context, Unexpected::ObjectClose) {
+                    match self.handle_end_number(ss, Position::ArrayValue, context, Pos(ss.position().0 - 1), Unexpected::ArrayClose) {
                         Ok(()) => (),
                         Err(err) => {
                             self.reentry_action = ReentryAction::FinishArrayClose;
@@ -292,7 +483,7 @@ impl ParserState {
                 }
                 self.finish_array_close(ss)?;
             },
-            _ => return unexpected!(ss, Unexpected::ObjectClose),
+            _ => return unexpected_token!(ss, Unexpected::ArrayClose),
         }
 
         Ok(())
@@ -300,12 +491,13 @@ impl ParserState {
 
     pub fn finish_array_close<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
         if !self.read_value {
-            ss.pop_into_array();
+            self.pop_into_array(ss);
         }
 
         self.read_value = false;
 
         self.stack.pop().unwrap();
+        self.members.pop();
 
         self.state = match self.stack.last() {
             Some(&StackState::Object) => TopState::ObjectCommaEnd,
@@ -327,15 +519,17 @@ impl ParserState {
         match self.state {
             TopState::ObjectCommaEnd if !self.read_value => {
                 self.state = TopState::ObjectKeyEnd;
-                ss.pop_into_map();
+                self.after_comma = true;
+                self.pop_into_map(ss);
             },
             TopState::ArrayCommaEnd if !self.read_value => {
                 self.read_value = true;
-                ss.pop_into_array();
+                self.after_comma = true;
+                self.pop_into_array(ss);
             },
             TopState::Number(context) => {
                 let position = self.get_position();
-                match self.handle_end_number(ss, position, context, Unexpected::Comma) {
+                match self.handle_end_number(ss, position, context, Pos(ss.position().0 - 1), Unexpected::Comma) {
                     Ok(()) => (),
                     Err(err) => {
                         self.reentry_action = ReentryAction::FinishNumberComma;
@@ -344,7 +538,7 @@ impl ParserState {
                 }
                 self.finish_number_token_comma(ss)?;
             },
-            _ => return unexpected!(ss, Unexpected::Comma),
+            _ => return unexpected_token!(ss, Unexpected::Comma),
         }
 
         Ok(())
@@ -354,13 +548,15 @@ impl ParserState {
         match self.state {
             TopState::ObjectCommaEnd => {
                 self.state = TopState::ObjectKeyEnd;
-                ss.pop_into_map();
+                self.after_comma = true;
+                self.pop_into_map(ss);
             },
             TopState::ArrayCommaEnd => {
                 self.read_value = true;
-                ss.pop_into_array();
+                self.after_comma = true;
+                self.pop_into_array(ss);
             },
-            _ => return unexpected!(ss, Unexpected::Comma),
+            _ => return unexpected_token!(ss, Unexpected::Comma),
         }
         Ok(())
     }
@@ -373,7 +569,7 @@ impl ParserState {
                 self.state = TopState::ObjectCommaEnd;
                 self.read_value = true;
             },
-            _ => return unexpected!(ss, Unexpected::Colon),
+            _ => return unexpected_token!(ss, Unexpected::Colon),
         }
 
         Ok(())
@@ -382,15 +578,20 @@ impl ParserState {
     pub fn token_exponent<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
         log_token("exponent");
 
-        if !matches!(self.state, TopState::Number(_)) {
-            return unexpected!(ss, Unexpected::Exponent);
+        if !self.continues_number(ss.position().0 - 1) {
+            return unexpected_token!(ss, Unexpected::Exponent);
         }
 
+        if self.number_state == NumberState::Decimal && self.end_fraction() {
+            self.number_state = NumberState::ExponentStartEnd;
+        }
         self.number_state = match self.number_state {
             NumberState::DotExponentEnd => NumberState::ExponentSign,
             NumberState::ExponentStartEnd => NumberState::ExponentSign,
-            _ => return unexpected!(ss, Unexpected::Exponent),
+            NumberState::Decimal => return unexpected_token!(ss, Unexpected::EmptyFraction),
+            _ => return unexpected_token!(ss, Unexpected::Exponent),
         };
+        self.number_end = ss.position().0;
 
         Ok(())
     }
@@ -398,11 +599,28 @@ impl ParserState {
     pub fn token_dot<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
         log_token("dot");
 
-        if !matches!(self.state, TopState::Number(_))
+        // JSON5 numbers can start with their decimal point, also after a
+        // sign.
+        let at = Pos(ss.position().0 - 1);
+        if self.json5() {
+            let after_sign = self.continues_number(at.0) && self.number_state == NumberState::Integer;
+            if after_sign || (self.read_value && !matches!(self.state, TopState::Number(_))) {
+                if !after_sign {
+                    self.start_number(at)?;
+                }
+                self.number_data.integer = Range::new(at, at);
+                self.number_state = NumberState::Decimal;
+                self.number_end = ss.position().0;
+                return Ok(());
+            }
+        }
+
+        if !self.continues_number(ss.position().0 - 1)
             || self.number_state != NumberState::DotExponentEnd {
-                return unexpected!(ss, Unexpected::Dot);
+                return unexpected_token!(ss, Unexpected::Dot);
             }
         self.number_state = NumberState::Decimal;
+        self.number_end = ss.position().0;
 
         Ok(())
     }
@@ -412,17 +630,27 @@ impl ParserState {
 
         match self.state {
             TopState::Number(_) => {
-                if self.number_state != NumberState::ExponentSign {
-                    return unexpected!(ss, Unexpected::Sign);
-                }
+                if self.number_state != NumberState::ExponentSign
+                    || !self.continues_number(ss.position().0 - 1) {
+                        return unexpected_token!(ss, Unexpected::Sign);
+                    }
                 self.number_data.exponent_sign = sign;
                 self.number_state = NumberState::Exponent;
+                self.number_end = ss.position().0;
             },
             _ => {
                 if !self.read_value {
-                    return unexpected!(ss, Unexpected::Sign);
+                    return unexpected_token!(ss, Unexpected::Sign);
+                }
+                if sign && self.rejects_extensions() {
+                    return unexpected_token!(ss, Unexpected::PlusSign);
+                }
+                if !self.count_member() {
+                    return unexpected_token!(ss, Unexpected::MemberLimit);
                 }
                 self.read_value = false;
+                self.started = true;
+                self.number_end = ss.position().0;
                 self.number_data = NumberData::default();
                 self.number_data.sign = sign;
                 self.state = TopState::Number(TopStateContext::from_topstate(self.state));
@@ -433,13 +661,21 @@ impl ParserState {
         Ok(())
     }
 
-    pub fn token_number<SS>(&mut self, ss: &mut SS, range: Range) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
+    /// A run of digits. `zero` tells whether the first one is `0`.
+    pub fn token_number<SS>(&mut self, ss: &mut SS, range: Range, zero: bool) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
         log_token("number");
 
         match self.state {
+            TopState::Number(_) if !self.continues_number(range.start) => {
+                return unexpected_at!(range.start.into(), Unexpected::Number);
+            },
             TopState::Number(context) => {
+                self.number_end = range.end;
                 match self.number_state {
                     NumberState::Integer => {
+                        if self.options.strict && zero && range.size() > 1 {
+                            return unexpected_at!(range.start.into(), Unexpected::LeadingZero);
+                        }
                         self.number_data.integer = range;
                         self.number_state = NumberState::DotExponentEnd;
                     },
@@ -451,6 +687,7 @@ impl ParserState {
                         self.number_data.exponent = Some(range);
                         self.state = match context {
                             TopStateContext::None => {
+                                self.state = TopState::None;
                                 lift_bail_sink!(ss.push_number(self.get_position(), self.number_data.clone()))?;
                                 return Err(ParseError::End);
                             }
@@ -460,14 +697,22 @@ impl ParserState {
                         };
                         lift_bail_sink!(ss.push_number(self.get_position(), self.number_data.clone()))?;
                     },
-                    _ => return unexpected!(ss, Unexpected::Number),
+                    _ => return unexpected_at!(range.start.into(), Unexpected::Number),
                 }
             },
             _ => {
                 if !self.read_value {
-                    return unexpected!(ss, Unexpected::Number);
+                    return unexpected_at!(range.start.into(), Unexpected::Number);
+                }
+                if self.options.strict && zero && range.size() > 1 {
+                    return unexpected_at!(range.start.into(), Unexpected::LeadingZero);
+                }
+                if !self.count_member() {
+                    return unexpected_at!(range.start.into(), Unexpected::MemberLimit);
                 }
                 self.read_value = false;
+                self.started = true;
+                self.number_end = range.end;
                 self.number_data = NumberData::default();
                 self.number_data.integer = range;
                 self.number_state = NumberState::DotExponentEnd;
@@ -478,13 +723,61 @@ impl ParserState {
         Ok(())
     }
 
+    /// The digits of a JSON5 hexadecimal number, after the `0x` that was
+    /// passed to `token_number` as `0`.
+    pub fn token_hex_number<SS>(&mut self, _ss: &mut SS, range: Range) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
+        log_token("hex_number");
+
+        let prefix = range.start - 1;
+        let after_zero = self.continues_number(prefix)
+            && self.number_state == NumberState::DotExponentEnd
+            && self.number_data.integer.size() == 1;
+        if !self.json5() || !after_zero {
+            return unexpected_at!(prefix.into(), Unexpected::Character);
+        }
+        self.number_data.kind = NumberKind::Hexadecimal;
+        self.number_data.integer = range;
+        self.number_state = NumberState::End;
+        self.number_end = range.end;
+        Ok(())
+    }
+
+    /// `Infinity` or `NaN`, which started at `start`. Only allowed in JSON5
+    /// and with `Options::non_finite`. Like for
+    /// the other literals, errors are reported at the current position.
+    pub fn token_special_number<SS>(&mut self, ss: &mut SS, kind: NumberKind, start: Pos) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
+        log_token("special_number");
+
+        let after_sign = self.continues_number(start.0) && self.number_state == NumberState::Integer;
+        let allowed = self.options.non_finite || self.json5();
+        if !allowed || (matches!(self.state, TopState::Number(_)) && !after_sign) {
+            return unexpected!(ss, Unexpected::Number);
+        }
+        // Only JSON5 allows a sign before NaN.
+        if after_sign && kind == NumberKind::NaN && !self.json5() {
+            return unexpected!(ss, Unexpected::Number);
+        }
+        if !after_sign {
+            self.start_number(ss.position())?;
+        }
+        self.number_data.kind = kind;
+        self.number_data.integer = Range::new(start, start);
+        self.number_state = NumberState::End;
+        self.number_end = ss.position().0;
+        Ok(())
+    }
+
     pub fn token_bool<SS>(&mut self, ss: &mut SS, value: bool) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
         log_token("bool");
 
         if !self.read_value {
             return unexpected!(ss, Unexpected::Bool);
         }
+        if !self.count_member() {
+            return unexpected!(ss, Unexpected::MemberLimit);
+        }
         self.read_value = false;
+        self.started = true;
 
         lift_bail_sink!(ss.push_bool(self.get_position(), value))?;
         if self.stack.len() == 0 {
@@ -498,9 +791,13 @@ impl ParserState {
         log_token("null");
 
         if !self.read_value {
-            return unexpected!(ss, Unexpected::Bool);
+            return unexpected!(ss, Unexpected::Null);
+        }
+        if !self.count_member() {
+            return unexpected!(ss, Unexpected::MemberLimit);
         }
         self.read_value = false;
+        self.started = true;
 
         lift_bail_sink!(ss.push_null(self.get_position()))?;
         if self.stack.len() == 0 {
@@ -511,6 +808,17 @@ impl ParserState {
     }
 
     pub fn token_quote<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
+        self.token_string_delimiter(ss, StringKind::DoubleQuoted)
+    }
+
+    /// Whether an object key is expected, which in JSON5 can be unquoted.
+    pub fn expects_key(&self) -> bool {
+        self.state == TopState::ObjectKeyEnd
+    }
+
+    /// Starts or ends a string written as `kind`. Unquoted keys are started
+    /// before their first character, and ended after their last.
+    pub fn token_string_delimiter<SS>(&mut self, ss: &mut SS, kind: StringKind) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
         log_token("quote");
 
         match self.state {
@@ -528,14 +836,29 @@ impl ParserState {
                 lift_bail_sink!(ss.finalize_string(context.string_position()))?;
             },
             _ => {
+                if kind == StringKind::Identifier && self.state != TopState::ObjectKeyEnd {
+                    return unexpected!(ss, Unexpected::Character);
+                }
                 if !self.read_value && !(self.state == TopState::ObjectKeyEnd) {
-                    return unexpected!(ss, Unexpected::Quote);
+                    return unexpected_token!(ss, Unexpected::Quote);
+                }
+                if !self.count_member() {
+                    return unexpected_token!(ss, Unexpected::MemberLimit);
+                }
+                if self.state != TopState::ObjectKeyEnd && self.start_skip(ss) {
+                    return Ok(());
                 }
 
                 self.read_value = false;
+                self.started = true;
+                self.string_start = match kind {
+                    StringKind::Identifier => ss.position().0,
+                    _ => ss.position().0 - 1,
+                };
+                self.string_length = 0;
                 let context = TopStateContext::from_topstate(self.state);
                 self.state = TopState::String(context);
-                ss.start_string(context.string_position());
+                ss.start_string_kind(context.string_position(), kind);
             },
         }
 
@@ -546,22 +869,69 @@ impl ParserState {
     pub fn token_string_range<SS>(&mut self, ss: &mut SS, range: Range) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
         log_token("string_range");
 
+        self.add_string_length(range.size())?;
         ss.append_string_range(range);
         Ok(())
     }
     pub fn token_string_single<SS>(&mut self, ss: &mut SS, byte: u8) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
         log_token("string_single");
 
+        self.add_string_length(1)?;
         ss.append_string_single(byte);
         Ok(())
     }
     pub fn token_string_codepoint<SS>(&mut self, ss: &mut SS, codepoint: char) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
         log_token("string_codepoint");
 
+        self.add_string_length(codepoint.len_utf8())?;
         ss.append_string_codepoint(codepoint);
         Ok(())
     }
 
+    fn add_string_length<B>(&mut self, length: usize) -> PResult<(), B> {
+        self.string_length += length;
+        if self.exceeds_string_limit(self.string_length) {
+            return unexpected_at!(Pos(self.string_start), Unexpected::StringLimit);
+        }
+        Ok(())
+    }
+
+    /// Checks the string limit, counting `pending` bytes of the current
+    /// string that the tokenizer has read but not yet passed on.
+    pub fn check_string_pending<B>(&self, pending: usize) -> PResult<(), B> {
+        if self.exceeds_string_limit(self.string_length + pending) {
+            return unexpected_at!(Pos(self.string_start), Unexpected::StringLimit);
+        }
+        Ok(())
+    }
+
+    /// Whether the sink asked to skip the value just started. The tokenizer
+    /// should scan past it, and then call `finish_skip`.
+    pub fn skipping(&self) -> bool {
+        self.skipping
+    }
+
+    /// Number of containers the parser is in.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Called by the tokenizer after scanning past a skipped value. Continues
+    /// as if the value had been read.
+    pub fn finish_skip<B>(&mut self) -> PResult<(), B> {
+        self.skipping = false;
+        self.value_skipped = true;
+        self.state = match self.stack.last() {
+            Some(&StackState::Object) => TopState::ObjectCommaEnd,
+            Some(&StackState::Array) => TopState::ArrayCommaEnd,
+            None => TopState::None,
+        };
+        if self.stack.is_empty() {
+            return Err(ParseError::End);
+        }
+        Ok(())
+    }
+
     pub fn reentry<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
         let action = self.reentry_action;
         self.reentry_action = ReentryAction::None;
@@ -575,12 +945,83 @@ impl ParserState {
     }
 
     pub fn finish<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
-        if self.state == TopState::Number(TopStateContext::None) {
-            self.handle_end_number(ss, Position::Root, TopStateContext::None, Unexpected::Eof)?;
+        self.end_root_number(ss, Unexpected::Eof)
+    }
+
+    /// Whether the root value is a number that has not ended yet.
+    pub fn in_root_number(&self) -> bool {
+        self.state == TopState::Number(TopStateContext::None)
+    }
+
+    /// Ends a root number at the current position, which is reported with
+    /// `reason` if the number is incomplete. Returns `Err(End)` if there
+    /// was a number to end.
+    pub fn end_root_number<SS>(&mut self, ss: &mut SS, reason: Unexpected) -> PResult<(), SS::Bail> where SS: Source + Sink + Bailable {
+        if self.in_root_number() {
+            let at = ss.position();
+            self.handle_end_number(ss, Position::Root, TopStateContext::None, at, reason)?;
         }
         Ok(())
     }
 
+    /// Earliest position referenced by a range the parser is holding on to
+    /// and has not yet passed to the sink.
+    pub fn retain_from(&self) -> Option<Pos> {
+        match self.state {
+            TopState::Number(_) if self.number_state != NumberState::Integer => {
+                Some(self.number_data.integer.start.into())
+            },
+            _ => None,
+        }
+    }
+
+    /// The tokens that would have been accepted in the current state.
+    /// After an error, describes what was expected instead.
+    pub fn expected(&self) -> Expectation {
+        use self::Expected as E;
+
+        let number_end: &[Expected] = match self.state {
+            TopState::Number(TopStateContext::ArrayValue) => &[E::Comma, E::ArrayClose],
+            TopState::Number(TopStateContext::ObjectValue) => &[E::Comma, E::ObjectClose],
+            _ => &[E::Eof],
+        };
+        let strict_comma = self.rejects_extensions() && self.after_comma;
+
+        match self.state {
+            TopState::None if !self.started => Expectation::new(&[E::Value], "at start of document"),
+            TopState::None => Expectation::new(&[E::Eof], "after document"),
+            TopState::ArrayCommaEnd if self.read_value && strict_comma =>
+                Expectation::new(&[E::Value], "after ','"),
+            TopState::ArrayCommaEnd if self.read_value =>
+                Expectation::new(&[E::Value, E::ArrayClose], "in array"),
+            TopState::ArrayCommaEnd => Expectation::new(&[E::Comma, E::ArrayClose], "after array value"),
+            TopState::ObjectKeyEnd if strict_comma => Expectation::new(&[E::Key], "after ','"),
+            TopState::ObjectKeyEnd => Expectation::new(&[E::Key, E::ObjectClose], "in object"),
+            TopState::ObjectColon => Expectation::new(&[E::Colon], "after object key"),
+            TopState::ObjectCommaEnd if self.read_value => Expectation::new(&[E::Value], "after ':'"),
+            TopState::ObjectCommaEnd => Expectation::new(&[E::Comma, E::ObjectClose], "after object value"),
+            TopState::String(_) => Expectation::new(&[E::Quote], "in string"),
+            TopState::Number(_) => {
+                let mut tokens = match self.number_state {
+                    NumberState::Integer | NumberState::Decimal | NumberState::Exponent => vec![E::Digit],
+                    NumberState::DotExponentEnd => vec![E::Dot, E::Exponent],
+                    NumberState::ExponentStartEnd => vec![E::Exponent],
+                    NumberState::ExponentSign => vec![E::Sign, E::Digit],
+                    NumberState::End => vec![],
+                };
+                if self.number_state == NumberState::DotExponentEnd
+                    || self.number_state == NumberState::ExponentStartEnd
+                    || self.number_state == NumberState::End {
+                        tokens.extend_from_slice(number_end);
+                    }
+                Expectation {
+                    tokens,
+                    context: Some("in number"),
+                }
+            },
+        }
+    }
+
     pub fn finished(&self) -> bool {
         self.state == TopState::None && self.stack.len() == 0 && !self.read_value && self.started
     }
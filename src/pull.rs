@@ -0,0 +1,229 @@
+use std::collections::VecDeque;
+use std::mem;
+
+use ::Bailable;
+use ::error::{ParseError, Unexpected};
+use ::input::{Pos, Range, Window, SourceSink, BailVariant};
+use ::options::Options;
+use ::parser::NumberData;
+use ::sink::{Sink, Position, StringPosition};
+use ::source::Source;
+use ::tokenizer::TokenizerState;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key(String),
+    String(String),
+    /// The ranges can be resolved against `Events::window`, as long as the
+    /// source has not discarded them.
+    Number(NumberData),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PullError<SourceBail> {
+    /// The source bailed. Give it more input and call `next` again to
+    /// continue.
+    NeedMoreInput(SourceBail),
+    /// The input is not valid JSON. No more events follow.
+    Unexpected(Pos, Unexpected),
+}
+
+// Sink queueing the events, and bailing after each one so that the
+// tokenizer returns control to the iterator.
+#[derive(Debug, Default)]
+struct EventQueue {
+    events: VecDeque<Event>,
+    string: Vec<u8>,
+    // Set by `Events::skip_value`, cleared by the next event.
+    skip: bool,
+}
+
+impl EventQueue {
+    fn emit(&mut self, event: Event) -> Result<(), ()> {
+        self.skip = false;
+        self.events.push_back(event);
+        Err(())
+    }
+
+    // Scalars are not asked about through `skip_value`, so they are dropped
+    // here instead.
+    fn emit_value(&mut self, event: Event) -> Result<(), ()> {
+        if mem::replace(&mut self.skip, false) {
+            return Err(());
+        }
+        self.emit(event)
+    }
+}
+
+impl Bailable for EventQueue {
+    type Bail = ();
+}
+
+impl Sink for EventQueue {
+    fn push_map(&mut self, _pos: Position) {
+        self.events.push_back(Event::StartObject);
+    }
+    fn push_array(&mut self, _pos: Position) {
+        self.events.push_back(Event::StartArray);
+    }
+
+    fn push_number(&mut self, _pos: Position, number: NumberData) -> Result<(), Self::Bail> {
+        self.emit_value(Event::Number(number))
+    }
+    fn push_bool(&mut self, _pos: Position, boolean: bool) -> Result<(), Self::Bail> {
+        self.emit_value(Event::Bool(boolean))
+    }
+    fn push_null(&mut self, _pos: Position) -> Result<(), Self::Bail> {
+        self.emit_value(Event::Null)
+    }
+
+    fn start_string(&mut self, _pos: StringPosition) {
+        self.string.clear();
+    }
+    fn append_string_range(&mut self, _string: Range) {
+        // `SourceSink` always passes the window.
+        unreachable!()
+    }
+    fn append_string_window(&mut self, string: Range, window: Window) {
+        // Ranges passed to the sink are always in the window.
+        self.string.extend_from_slice(window.get(string).unwrap());
+    }
+    fn append_string_single(&mut self, character: u8) {
+        self.string.push(character);
+    }
+    fn append_string_codepoint(&mut self, codepoint: char) {
+        let mut buf = [0; 4];
+        self.string.extend_from_slice(codepoint.encode_utf8(&mut buf).as_bytes());
+    }
+    fn finalize_string(&mut self, pos: StringPosition) -> Result<(), Self::Bail> {
+        // The tokenizer only passes on valid UTF-8.
+        let string = String::from_utf8(mem::take(&mut self.string)).unwrap();
+        match pos {
+            StringPosition::MapKey => self.emit(Event::Key(string)),
+            _ => self.emit(Event::String(string)),
+        }
+    }
+
+    fn finalize_array(&mut self, _pos: Position) -> Result<(), Self::Bail> {
+        self.emit(Event::EndArray)
+    }
+    fn finalize_map(&mut self, _pos: Position) -> Result<(), Self::Bail> {
+        self.emit(Event::EndObject)
+    }
+
+    fn pop_into_map(&mut self) {}
+    fn pop_into_array(&mut self) {}
+
+    fn skip_value(&mut self, _pos: Position) -> bool {
+        mem::replace(&mut self.skip, false)
+    }
+}
+
+/// Pull style driver for the tokenizer, iterating over the events of a
+/// document.
+///
+/// The tokenizer is only run when the events read so far have been taken,
+/// and stops after the next event. When the source bails, the iterator
+/// yields `PullError::NeedMoreInput`. Feed the source through `source_mut`
+/// and keep iterating to continue.
+#[derive(Debug)]
+pub struct Events<Src> where Src: Source {
+    tokenizer: TokenizerState,
+    ss: SourceSink<Src, EventQueue>,
+    // Error to return once the events before it have been taken.
+    error: Option<PullError<Src::Bail>>,
+    done: bool,
+}
+
+impl<Src> Events<Src> where Src: Source {
+
+    pub fn new(source: Src) -> Events<Src> {
+        Events::with_options(source, Options::default())
+    }
+
+    pub fn with_options(source: Src, options: Options) -> Events<Src> {
+        Events {
+            tokenizer: TokenizerState::with_options(options),
+            ss: SourceSink {
+                source,
+                sink: EventQueue::default(),
+            },
+            error: None,
+            done: false,
+        }
+    }
+
+    pub fn source(&self) -> &Src {
+        &self.ss.source
+    }
+
+    pub fn source_mut(&mut self) -> &mut Src {
+        &mut self.ss.source
+    }
+
+    pub fn into_source(self) -> Src {
+        self.ss.source
+    }
+
+    /// The window of the source, to resolve the ranges of `Event::Number`.
+    pub fn window(&self) -> Window<'_> {
+        self.ss.source.window()
+    }
+
+    /// Skips the next value without producing events for it, scanning past
+    /// it without decoding or checking its contents, see `Sink::skip_value`.
+    /// Call it right after a `Key` event, or after any event completing a
+    /// value to skip the next element of the enclosing array.
+    ///
+    /// Returns false, doing nothing, if the next value has already started,
+    /// as is the case after `StartArray` or `StartObject` events.
+    pub fn skip_value(&mut self) -> bool {
+        if !self.ss.sink.events.is_empty() || self.error.is_some() || self.done {
+            return false;
+        }
+        self.ss.sink.skip = true;
+        true
+    }
+
+    fn run(&mut self) {
+        match self.tokenizer.run(&mut self.ss) {
+            Ok(()) => self.done = true,
+            Err(ParseError::SourceBail(BailVariant::Sink(()))) => (),
+            Err(ParseError::SourceBail(BailVariant::Source(bail))) => {
+                self.error = Some(PullError::NeedMoreInput(bail));
+            },
+            Err(ParseError::Unexpected(pos, reason)) => {
+                self.error = Some(PullError::Unexpected(pos, reason));
+                self.done = true;
+            },
+            // The tokenizer only returns these internally.
+            Err(ParseError::End) | Err(ParseError::Eof) => unreachable!(),
+        }
+    }
+
+}
+
+impl<Src> Iterator for Events<Src> where Src: Source {
+    type Item = Result<Event, PullError<Src::Bail>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.ss.sink.events.pop_front() {
+                return Some(Ok(event));
+            }
+            if let Some(error) = self.error.take() {
+                return Some(Err(error));
+            }
+            if self.done {
+                return None;
+            }
+            self.run();
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use ::PResult;
+use ::error::ParseError;
+use ::input::{SourceSink, BailVariant};
+use ::sink::Sink;
+use ::source::chunked::ChunkSource;
+use ::tokenizer::TokenizerState;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// All input fed so far has been consumed, feed more.
+    NeedMoreData,
+    /// A complete value has been read.
+    Done,
+}
+
+/// Push style driver for the tokenizer.
+///
+/// Input is handed over with `feed` as it arrives, in chunks of any size.
+/// Events are emitted to the sink as soon as the input for them is
+/// available. Once the input is exhausted, `finish` must be called to
+/// complete values that are only terminated by EOF (root numbers), and to
+/// detect truncated documents.
+///
+/// When the sink bails, the bail is returned as `ParseError::SourceBail`.
+/// Parsing continues where it left off on the next call to `feed` (with an
+/// empty chunk if there is no new input) or `finish`.
+#[derive(Debug)]
+pub struct PushParser<S> where S: Sink {
+    tokenizer: TokenizerState,
+    ss: SourceSink<ChunkSource, S>,
+}
+
+impl<S> PushParser<S> where S: Sink {
+
+    pub fn new(sink: S) -> PushParser<S> {
+        PushParser::with_tokenizer(TokenizerState::new(), sink)
+    }
+
+    pub fn with_tokenizer(tokenizer: TokenizerState, sink: S) -> PushParser<S> {
+        PushParser {
+            tokenizer,
+            ss: SourceSink {
+                source: ChunkSource::new(),
+                sink,
+            },
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> PResult<Status, S::Bail> {
+        self.ss.source.push(chunk);
+        self.run()
+    }
+
+    pub fn finish(&mut self) -> PResult<(), S::Bail> {
+        self.ss.source.finish();
+        match self.run()? {
+            Status::Done => Ok(()),
+            // A finished source never bails.
+            Status::NeedMoreData => unreachable!(),
+        }
+    }
+
+    fn run(&mut self) -> PResult<Status, S::Bail> {
+        match self.tokenizer.run(&mut self.ss) {
+            Ok(()) => Ok(Status::Done),
+            Err(ParseError::SourceBail(BailVariant::Source(()))) => Ok(Status::NeedMoreData),
+            Err(ParseError::SourceBail(BailVariant::Sink(bail))) => Err(ParseError::SourceBail(bail)),
+            Err(ParseError::Unexpected(pos, reason)) => Err(ParseError::Unexpected(pos, reason)),
+            // The tokenizer only returns these internally.
+            Err(ParseError::End) | Err(ParseError::Eof) => unreachable!(),
+        }
+    }
+
+    /// All input fed so far. Ranges handed to the sink index into this.
+    pub fn buffer(&self) -> &[u8] {
+        self.ss.source.buffer()
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.ss.sink
+    }
+
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.ss.sink
+    }
+
+    pub fn into_sink(self) -> S {
+        self.ss.sink
+    }
+
+}
@@ -0,0 +1,172 @@
+//! Human readable error reports.
+//!
+//! Errors from the parser only carry a byte offset. A `Report` re-scans the
+//! input to turn that into a line and column, and renders the offending
+//! line with a caret under the error:
+//!
+//! ```text
+//! line 1, column 9: expected ',' or '}' after object value, found ']'
+//!   |
+//! 1 | {"a": 12]
+//!   |         ^
+//! ```
+
+use std::fmt;
+
+use ::error::{Unexpected, Expectation};
+use ::input::Pos;
+
+// Lines longer than this are cut down to the part around the error.
+const MAX_SNIPPET: usize = 80;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub pos: Pos,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column, counted in characters. Invalid UTF-8 counts like
+    /// the replacement characters the snippet shows for it.
+    pub column: usize,
+    pub reason: Unexpected,
+    pub expected: Expectation,
+    /// The byte at the error position, `None` at the end of the input.
+    pub found: Option<u8>,
+
+    snippet: String,
+    caret: usize,
+}
+
+impl Report {
+
+    /// Builds a report for an error at `pos` in `input`. `input` must be the
+    /// complete input from the start, `expected` is usually taken from
+    /// `TokenizerState::expected` right after the error.
+    pub fn new(input: &[u8], pos: Pos, reason: Unexpected, expected: Expectation) -> Report {
+        let pos_idx = ::std::cmp::min(pos.0, input.len());
+
+        let line_start = input[..pos_idx].iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |idx| idx + 1);
+        let line_end = input[pos_idx..].iter()
+            .position(|&b| b == b'\n' || b == b'\r')
+            .map_or(input.len(), |idx| pos_idx + idx);
+
+        let line = input[..line_start].iter().filter(|&&b| b == b'\n').count() + 1;
+        let column = char_count(&input[line_start..line_end], pos_idx - line_start) + 1;
+
+        let text = String::from_utf8_lossy(&input[line_start..line_end]);
+        let mut chars: Vec<char> = text.chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        let mut caret = column - 1;
+
+        let mut snippet = String::new();
+        if chars.len() > MAX_SNIPPET {
+            let start = caret.saturating_sub(MAX_SNIPPET / 2);
+            let end = ::std::cmp::min(start + MAX_SNIPPET, chars.len());
+            if start > 0 {
+                snippet.push_str("...");
+                caret = caret - start + 3;
+            }
+            let truncated = end < chars.len();
+            chars.truncate(end);
+            snippet.extend(&chars[start..]);
+            if truncated {
+                snippet.push_str("...");
+            }
+        } else {
+            snippet.extend(&chars);
+        }
+
+        Report {
+            pos,
+            line,
+            column,
+            reason,
+            expected,
+            found: input.get(pos.0).cloned(),
+            snippet,
+            caret,
+        }
+    }
+
+    /// The one line description of the error, without position.
+    pub fn message(&self) -> String {
+        if !describes_token(self.reason) || self.expected.tokens.is_empty() {
+            return self.reason.explain().to_owned();
+        }
+
+        let mut out = "expected ".to_owned();
+        let tokens = &self.expected.tokens;
+        for (idx, token) in tokens.iter().enumerate() {
+            if idx > 0 {
+                out.push_str(if idx == tokens.len() - 1 { " or " } else { ", " });
+            }
+            out.push_str(&token.to_string());
+        }
+        if let Some(context) = self.expected.context {
+            out.push(' ');
+            out.push_str(context);
+        }
+        out.push_str(", found ");
+        match self.found {
+            None => out.push_str("end of input"),
+            Some(byte) if (0x20..0x7f).contains(&byte) => {
+                out.push('\'');
+                out.push(byte as char);
+                out.push('\'');
+            },
+            Some(byte) => out.push_str(&format!("byte 0x{:02x}", byte)),
+        }
+        out
+    }
+
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let gutter = " ".repeat(self.line.to_string().len());
+        writeln!(f, "line {}, column {}: {}", self.line, self.column, self.message())?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", self.line, self.snippet)?;
+        write!(f, "{} | {}^", gutter, " ".repeat(self.caret))
+    }
+}
+
+// The number of characters before `offset` in `line`, counted like
+// `String::from_utf8_lossy` renders them, so that the caret lines up with
+// the snippet.
+fn char_count(line: &[u8], offset: usize) -> usize {
+    let mut count = 0;
+    let mut read = 0;
+    for chunk in line.utf8_chunks() {
+        for character in chunk.valid().chars() {
+            if read >= offset {
+                return count;
+            }
+            read += character.len_utf8();
+            count += 1;
+        }
+        // An offset inside a run of invalid bytes points at its
+        // replacement character.
+        read += chunk.invalid().len();
+        if read > offset {
+            return count;
+        }
+        if !chunk.invalid().is_empty() {
+            count += 1;
+        }
+    }
+    count
+}
+
+// Whether the error is about a token showing up where it is not allowed,
+// as opposed to a token that is malformed in itself.
+fn describes_token(reason: Unexpected) -> bool {
+    !matches!(reason,
+              Unexpected::TrailingComma | Unexpected::PlusSign | Unexpected::LeadingZero
+              | Unexpected::EmptyFraction | Unexpected::EmptyExponent
+              | Unexpected::DepthLimit | Unexpected::StringLimit
+              | Unexpected::NumberLimit | Unexpected::MemberLimit
+              | Unexpected::DocumentLimit | Unexpected::InvalidUtf8)
+}
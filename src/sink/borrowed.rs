@@ -0,0 +1,162 @@
+use std::borrow::Cow;
+use std::mem;
+
+use ::Bailable;
+use super::{Sink, NumberData, Position, StringPosition};
+use super::value::{Value, Map, Number, DuplicateKeys, ValueError};
+use super::build::Builder;
+use super::path::PathSink;
+use ::input::{Range, Window};
+
+/// A JSON value borrowing its strings from the input where possible.
+///
+/// Strings without escapes borrow from the input. Strings with escapes, and
+/// all numbers, are converted when read.
+pub type BorrowedValue<'a> = Value<Cow<'a, str>>;
+
+pub type BorrowedMap<'a> = Map<Cow<'a, str>>;
+
+#[derive(Debug)]
+enum PendingString<'a> {
+    // Nothing appended yet.
+    Empty,
+    // A single range of the input.
+    Borrowed(&'a [u8]),
+    // Anything else.
+    Owned(Vec<u8>),
+}
+
+/// Sink building a `BorrowedValue` from a slice.
+///
+/// Ranges are resolved against the slice given on creation, which must be
+/// the whole input, as with `SliceSource`.
+#[derive(Debug)]
+pub struct BorrowedValueSink<'a> {
+    input: &'a [u8],
+    builder: Builder<Cow<'a, str>>,
+    string: PendingString<'a>,
+    // Set when a part of the current string was not in the input.
+    unavailable: bool,
+}
+
+impl<'a> BorrowedValueSink<'a> {
+    pub fn new(input: &'a [u8]) -> BorrowedValueSink<'a> {
+        BorrowedValueSink::with_duplicate_keys(input, DuplicateKeys::default())
+    }
+
+    pub fn with_duplicate_keys(input: &'a [u8], duplicate_keys: DuplicateKeys) -> BorrowedValueSink<'a> {
+        BorrowedValueSink {
+            input,
+            builder: Builder::new(duplicate_keys),
+            string: PendingString::Empty,
+            unavailable: false,
+        }
+    }
+
+    /// The value read, if a complete document has been read.
+    pub fn into_value(self) -> Option<BorrowedValue<'a>> {
+        self.builder.into_value()
+    }
+
+    // Continues the current string in an owned buffer.
+    fn owned(&mut self) -> &mut Vec<u8> {
+        let bytes = match mem::replace(&mut self.string, PendingString::Empty) {
+            PendingString::Empty => Vec::new(),
+            PendingString::Borrowed(bytes) => bytes.to_vec(),
+            PendingString::Owned(bytes) => bytes,
+        };
+        self.string = PendingString::Owned(bytes);
+        match self.string {
+            PendingString::Owned(ref mut bytes) => bytes,
+            _ => unreachable!(),
+        }
+    }
+
+    fn take_string(&mut self) -> Result<Cow<'a, str>, ValueError> {
+        let string = mem::replace(&mut self.string, PendingString::Empty);
+        if mem::replace(&mut self.unavailable, false) {
+            return Err(ValueError::Unavailable);
+        }
+        // The tokenizer only passes on valid UTF-8.
+        Ok(match string {
+            PendingString::Empty => Cow::Borrowed(""),
+            PendingString::Borrowed(bytes) => String::from_utf8_lossy(bytes),
+            PendingString::Owned(bytes) => match String::from_utf8(bytes) {
+                Ok(string) => Cow::Owned(string),
+                Err(err) => Cow::Owned(String::from_utf8_lossy(err.as_bytes()).into_owned()),
+            },
+        })
+    }
+}
+
+impl<'a> Bailable for BorrowedValueSink<'a> {
+    type Bail = ValueError;
+}
+
+impl<'a> PathSink for BorrowedValueSink<'a> {}
+
+impl<'a> Sink for BorrowedValueSink<'a> {
+    fn push_map(&mut self, _pos: Position) {
+        self.builder.push(Value::Object(Map::new()));
+    }
+    fn push_array(&mut self, _pos: Position) {
+        self.builder.push(Value::Array(Vec::new()));
+    }
+
+    fn push_number(&mut self, _pos: Position, number: NumberData) -> Result<(), Self::Bail> {
+        let number = Number::from_data(&number, Window::from(self.input)).map_err(ValueError::Number)?;
+        self.builder.push(Value::Number(number));
+        Ok(())
+    }
+    fn push_bool(&mut self, _pos: Position, boolean: bool) -> Result<(), Self::Bail> {
+        self.builder.push(Value::Bool(boolean));
+        Ok(())
+    }
+    fn push_null(&mut self, _pos: Position) -> Result<(), Self::Bail> {
+        self.builder.push(Value::Null);
+        Ok(())
+    }
+
+    fn start_string(&mut self, _pos: StringPosition) {
+        self.string = PendingString::Empty;
+        self.unavailable = false;
+    }
+    fn append_string_range(&mut self, string: Range) {
+        let bytes = match Window::from(self.input).get(string) {
+            Some(bytes) => bytes,
+            None => {
+                self.unavailable = true;
+                return;
+            }
+        };
+        match self.string {
+            PendingString::Empty => self.string = PendingString::Borrowed(bytes),
+            _ => self.owned().extend_from_slice(bytes),
+        }
+    }
+    fn append_string_single(&mut self, character: u8) {
+        self.owned().push(character);
+    }
+    fn append_string_codepoint(&mut self, codepoint: char) {
+        let mut buf = [0; 4];
+        self.owned().extend_from_slice(codepoint.encode_utf8(&mut buf).as_bytes());
+    }
+    fn finalize_string(&mut self, pos: StringPosition) -> Result<(), Self::Bail> {
+        let string = self.take_string()?;
+        self.builder.push_string(pos, string)
+    }
+
+    fn finalize_array(&mut self, _pos: Position) -> Result<(), Self::Bail> {
+        Ok(())
+    }
+    fn finalize_map(&mut self, _pos: Position) -> Result<(), Self::Bail> {
+        Ok(())
+    }
+
+    fn pop_into_map(&mut self) {
+        self.builder.pop_into_map();
+    }
+    fn pop_into_array(&mut self) {
+        self.builder.pop_into_array();
+    }
+}
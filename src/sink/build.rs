@@ -0,0 +1,88 @@
+use std::borrow::Borrow;
+
+use super::StringPosition;
+use super::value::{Value, DuplicateKeys, ValueError};
+
+/// Assembles the values read into a tree. Shared by the sinks building a
+/// `Value`, which only differ in how they read strings.
+#[derive(Debug)]
+pub struct Builder<S> {
+    duplicate_keys: DuplicateKeys,
+    stack: Vec<Value<S>>,
+    // Keys of the objects being read, waiting for their values.
+    keys: Vec<S>,
+}
+
+impl<S> Default for Builder<S> {
+    fn default() -> Self {
+        Builder::new(DuplicateKeys::default())
+    }
+}
+
+impl<S> Builder<S> {
+    pub fn new(duplicate_keys: DuplicateKeys) -> Builder<S> {
+        Builder {
+            duplicate_keys,
+            stack: Vec::new(),
+            keys: Vec::new(),
+        }
+    }
+
+    /// The value read, if a complete document has been read.
+    pub fn into_value(mut self) -> Option<Value<S>> {
+        if self.stack.len() == 1 && self.keys.is_empty() {
+            self.stack.pop()
+        } else {
+            None
+        }
+    }
+
+    pub fn push(&mut self, value: Value<S>) {
+        self.stack.push(value);
+    }
+}
+
+impl<S> Builder<S> where S: Borrow<str> {
+    /// Adds a string value, or the key of the next member.
+    pub fn push_string(&mut self, pos: StringPosition, string: S) -> Result<(), ValueError> {
+        if pos != StringPosition::MapKey {
+            self.stack.push(Value::String(string));
+            return Ok(());
+        }
+
+        let duplicate = match self.stack.last() {
+            Some(Value::Object(map)) => map.contains_key(string.borrow()),
+            _ => false,
+        };
+        if duplicate && self.duplicate_keys == DuplicateKeys::Reject {
+            let err = ValueError::DuplicateKey(string.borrow().to_string());
+            self.keys.push(string);
+            return Err(err);
+        }
+        self.keys.push(string);
+        Ok(())
+    }
+
+    pub fn pop_into_map(&mut self) {
+        let (value, key) = match (self.stack.pop(), self.keys.pop()) {
+            (Some(value), Some(key)) => (value, key),
+            _ => return,
+        };
+        if let Some(&mut Value::Object(ref mut map)) = self.stack.last_mut() {
+            if self.duplicate_keys == DuplicateKeys::First && map.contains_key(key.borrow()) {
+                return;
+            }
+            map.insert(key, value);
+        }
+    }
+
+    pub fn pop_into_array(&mut self) {
+        let value = match self.stack.pop() {
+            Some(value) => value,
+            None => return,
+        };
+        if let Some(&mut Value::Array(ref mut array)) = self.stack.last_mut() {
+            array.push(value);
+        }
+    }
+}
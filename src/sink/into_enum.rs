@@ -1,6 +1,7 @@
 use ::Bailable;
-use super::{Sink, NumberData, Position, StringPosition};
-use ::input::Range;
+use super::{Sink, NumberData, NumberKind, Position, StringPosition};
+use ::input::{Range, Window};
+use ::number::Decimal;
 
 #[derive(Debug, PartialEq)]
 pub enum Json {
@@ -12,6 +13,9 @@ pub enum Json {
     // converted into an integer or float using some unspecified
     // algorithm.
     Number(String),
+    // Numbers are stored exactly if the sink was created with
+    // `with_decimals`.
+    Decimal(Decimal),
     Boolean(bool),
     Null,
 }
@@ -24,6 +28,7 @@ pub struct EnumSink<'a> {
     source: &'a [u8],
     current_string: Vec<u8>,
     bail: bool,
+    decimals: bool,
 }
 
 impl<'a> EnumSink<'a> {
@@ -33,6 +38,7 @@ impl<'a> EnumSink<'a> {
             source: source,
             current_string: Vec::new(),
             bail: false,
+            decimals: false,
         }
     }
     pub fn new_bailing(source: &'a [u8]) -> EnumSink {
@@ -41,8 +47,22 @@ impl<'a> EnumSink<'a> {
         sink
     }
 
-    fn range_to_str<'b>(&'b mut self, range: Range) -> &'b str {
-        let raw = &self.source[(range.start)..(range.end)];
+    /// Creates a sink that does not borrow the input. Ranges are resolved
+    /// against the window of the source instead, which makes this usable
+    /// with sources that own their data.
+    pub fn new_windowed() -> EnumSink<'static> {
+        EnumSink::new(&[])
+    }
+
+    /// Stores numbers as `Json::Decimal` instead of strings. Numbers with an
+    /// exponent too large for `Decimal` are still stored as strings.
+    pub fn with_decimals(mut self) -> EnumSink<'a> {
+        self.decimals = true;
+        self
+    }
+
+    fn range_to_str<'b>(window: Window<'b>, range: Range) -> &'b str {
+        let raw = window.get(range).unwrap();
         ::std::str::from_utf8(raw).unwrap()
     }
 
@@ -84,7 +104,30 @@ impl<'a> Sink for EnumSink<'a> {
             _ => panic!(),
         }
     }
-    fn push_number(&mut self, _pos: Position, number: NumberData) -> Result<(), Self::Bail> {
+    fn push_number(&mut self, pos: Position, number: NumberData) -> Result<(), Self::Bail> {
+        let window = Window::from(self.source);
+        self.push_number_window(pos, number, window)
+    }
+    fn push_number_window(&mut self, _pos: Position, number: NumberData, window: Window) -> Result<(), Self::Bail> {
+        let special = match number.kind {
+            NumberKind::Decimal => None,
+            NumberKind::Hexadecimal => Some(format!("0x{}", EnumSink::range_to_str(window, number.integer))),
+            NumberKind::Infinity => Some("Infinity".to_string()),
+            NumberKind::NaN => Some("NaN".to_string()),
+        };
+        if let Some(special) = special {
+            let sign = if number.sign { '+' } else { '-' };
+            self.stack.push(Json::Number(format!("{}{}", sign, special)));
+            return if self.bail { Err(()) } else { Ok(()) };
+        }
+
+        if self.decimals {
+            if let Ok(decimal) = number.to_decimal(window) {
+                self.stack.push(Json::Decimal(decimal));
+                return if self.bail { Err(()) } else { Ok(()) };
+            }
+        }
+
         let mut out = String::new();
 
         if number.sign {
@@ -93,12 +136,12 @@ impl<'a> Sink for EnumSink<'a> {
             out.push('-');
         }
 
-        out.push_str(self.range_to_str(number.integer));
+        out.push_str(EnumSink::range_to_str(window, number.integer));
 
         out.push('.');
 
         if let Some(range) = number.decimal {
-            out.push_str(self.range_to_str(range));
+            out.push_str(EnumSink::range_to_str(window, range));
         } else {
             out.push('0');
         }
@@ -112,7 +155,7 @@ impl<'a> Sink for EnumSink<'a> {
         }
 
         if let Some(range) = number.exponent {
-            out.push_str(self.range_to_str(range));
+            out.push_str(EnumSink::range_to_str(window, range));
         } else {
             out.push('1')
         }
@@ -144,7 +187,11 @@ impl<'a> Sink for EnumSink<'a> {
 
     fn start_string(&mut self, _pos: StringPosition) {}
     fn append_string_range(&mut self, string: Range) {
-        let range = &self.source[(string.start)..(string.end)];
+        let window = Window::from(self.source);
+        self.append_string_window(string, window);
+    }
+    fn append_string_window(&mut self, string: Range, window: Window) {
+        let range = window.get(string).unwrap();
         self.current_string.extend_from_slice(range);
     }
     fn append_string_single(&mut self, character: u8) {
@@ -1,9 +1,17 @@
 use ::Bailable;
-use ::input::Range;
-pub use ::parser::NumberData;
+use ::input::{Pos, Range, Window};
+use ::error::Unexpected;
+pub use ::parser::{NumberData, NumberKind};
 
 pub mod debug_print;
 pub mod into_enum;
+pub mod value;
+pub mod borrowed;
+mod build;
+pub mod tape;
+pub mod pointer;
+pub mod query;
+pub mod path;
 
 pub trait Sink: Bailable {
     fn push_map(&mut self, pos: Position);
@@ -24,6 +32,61 @@ pub trait Sink: Bailable {
 
     fn pop_into_map(&mut self);
     fn pop_into_array(&mut self);
+
+    /// Same as `push_number`, but also receives the window of the source the
+    /// ranges in `number` point into. `SourceSink` always calls this version,
+    /// sinks that do not have access to the input themselves should
+    /// override it.
+    fn push_number_window(&mut self, pos: Position, number: NumberData, _window: Window) -> Result<(), Self::Bail> {
+        self.push_number(pos, number)
+    }
+    /// Same as `append_string_range`, see `push_number_window`.
+    fn append_string_window(&mut self, string: Range, _window: Window) {
+        self.append_string_range(string)
+    }
+    /// Same as `start_string`, but also tells how the string is written.
+    /// The parser always calls this version. Strings are only written other
+    /// than in double quotes in JSON5.
+    fn start_string_kind(&mut self, pos: StringPosition, _kind: StringKind) {
+        self.start_string(pos)
+    }
+
+    /// Called with the range of each comment, including the `//` or `/*`
+    /// and `*/` around it, see `Options::comments`. Comments in skipped
+    /// values are not passed on.
+    fn comment(&mut self, _comment: Range) {}
+    /// Same as `comment`, see `push_number_window`.
+    fn comment_window(&mut self, comment: Range, _window: Window) {
+        self.comment(comment)
+    }
+
+    /// Called when an object, array or string value is about to start.
+    /// Returning true skips it, and the sink gets no calls for the value at
+    /// all.
+    /// That includes the `pop_into_map` or `pop_into_array` that would
+    /// follow it, so a sink keeping the key of the value must drop it here.
+    ///
+    /// A skipped value is only scanned for its end. Brackets, quotes and
+    /// comments are matched and the depth limit applies, but nothing else in
+    /// it is checked: `[tru, "\q"]` is skipped without an error.
+    fn skip_value(&mut self, _pos: Position) -> bool {
+        false
+    }
+
+    /// Called before each document when the input holds several, see
+    /// `Documents`, with the position of its first byte.
+    fn start_document(&mut self, _start: Pos) {}
+    /// Called after each document when the input holds several, with the
+    /// range of input it took up.
+    fn end_document(&mut self, _document: Range) -> Result<(), Self::Bail> {
+        Ok(())
+    }
+    /// Called instead of `end_document` for a document that failed to
+    /// parse, with the range of input skipped, and where and why it failed.
+    /// Events for the part before the error have already been passed on.
+    fn document_error(&mut self, _document: Range, _pos: Pos, _reason: Unexpected) -> Result<(), Self::Bail> {
+        Ok(())
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -41,6 +104,15 @@ pub enum StringPosition {
     ArrayValue,
 }
 
+/// How a string is written in the input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum StringKind {
+    DoubleQuoted,
+    SingleQuoted,
+    /// An unquoted object key.
+    Identifier,
+}
+
 impl StringPosition {
     pub fn to_position(self) -> Position {
         match self {
@@ -51,3 +123,85 @@ impl StringPosition {
         }
     }
 }
+
+impl<S> Bailable for &mut S where S: Sink {
+    type Bail = S::Bail;
+}
+
+impl<S> Sink for &mut S where S: Sink {
+    fn push_map(&mut self, pos: Position) {
+        (**self).push_map(pos)
+    }
+    fn push_array(&mut self, pos: Position) {
+        (**self).push_array(pos)
+    }
+
+    fn push_number(&mut self, pos: Position, integer: NumberData) -> Result<(), Self::Bail> {
+        (**self).push_number(pos, integer)
+    }
+    fn push_bool(&mut self, pos: Position, boolean: bool) -> Result<(), Self::Bail> {
+        (**self).push_bool(pos, boolean)
+    }
+    fn push_null(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        (**self).push_null(pos)
+    }
+
+    fn start_string(&mut self, pos: StringPosition) {
+        (**self).start_string(pos)
+    }
+    fn start_string_kind(&mut self, pos: StringPosition, kind: StringKind) {
+        (**self).start_string_kind(pos, kind)
+    }
+    fn append_string_range(&mut self, string: Range) {
+        (**self).append_string_range(string)
+    }
+    fn append_string_single(&mut self, character: u8) {
+        (**self).append_string_single(character)
+    }
+    fn append_string_codepoint(&mut self, codepoint: char) {
+        (**self).append_string_codepoint(codepoint)
+    }
+    fn finalize_string(&mut self, pos: StringPosition) -> Result<(), Self::Bail> {
+        (**self).finalize_string(pos)
+    }
+
+    fn finalize_array(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        (**self).finalize_array(pos)
+    }
+    fn finalize_map(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        (**self).finalize_map(pos)
+    }
+
+    fn pop_into_map(&mut self) {
+        (**self).pop_into_map()
+    }
+    fn pop_into_array(&mut self) {
+        (**self).pop_into_array()
+    }
+
+    fn comment(&mut self, comment: Range) {
+        (**self).comment(comment)
+    }
+    fn comment_window(&mut self, comment: Range, window: Window) {
+        (**self).comment_window(comment, window)
+    }
+    fn skip_value(&mut self, pos: Position) -> bool {
+        (**self).skip_value(pos)
+    }
+    fn start_document(&mut self, start: Pos) {
+        (**self).start_document(start)
+    }
+    fn end_document(&mut self, document: Range) -> Result<(), Self::Bail> {
+        (**self).end_document(document)
+    }
+    fn document_error(&mut self, document: Range, pos: Pos, reason: Unexpected) -> Result<(), Self::Bail> {
+        (**self).document_error(document, pos, reason)
+    }
+
+    fn push_number_window(&mut self, pos: Position, number: NumberData, window: Window) -> Result<(), Self::Bail> {
+        (**self).push_number_window(pos, number, window)
+    }
+    fn append_string_window(&mut self, string: Range, window: Window) {
+        (**self).append_string_window(string, window)
+    }
+}
@@ -0,0 +1,452 @@
+use std::error;
+use std::fmt;
+use std::mem;
+
+use ::Bailable;
+use super::{Sink, NumberData, Position, StringPosition, StringKind};
+use super::pointer::Pointer;
+use ::input::{Pos, Range, Window};
+use ::error::Unexpected;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Location of a value in a document, as the keys and indices leading to
+/// it from the root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Path {
+    segments: Vec<Segment>,
+}
+
+impl Path {
+    pub fn root() -> Path {
+        Path::default()
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn to_pointer(&self) -> Pointer {
+        Pointer::from_tokens(self.segments.iter().map(|segment| match *segment {
+            Segment::Key(ref key) => key.clone(),
+            Segment::Index(index) => index.to_string(),
+        }).collect())
+    }
+}
+
+/// Formats the path in JSONPath notation, like `$.items[3]['a b']`.
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("$")?;
+        for segment in &self.segments {
+            match *segment {
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+                Segment::Key(ref key) if is_identifier(key) => write!(f, ".{}", key)?,
+                Segment::Key(ref key) => {
+                    let escaped = key.replace('\\', "\\\\").replace('\'', "\\'");
+                    write!(f, "['{}']", escaped)?
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_identifier(key: &str) -> bool {
+    key.chars().next().is_some_and(|first| first.is_alphabetic() || first == '_')
+        && key.chars().all(|character| character.is_alphanumeric() || character == '_')
+}
+
+/// Sink that can be told the path of each event, see `PathTracking`.
+pub trait PathSink: Sink {
+    /// Called by `PathTracking` before each event, with the path of the
+    /// value the event is about. Key events get the path of their object.
+    /// Sinks only using `PathTracking::attach` can leave this out.
+    fn at_path(&mut self, _path: &Path) {}
+}
+
+impl<S> PathSink for &mut S where S: PathSink {
+    fn at_path(&mut self, path: &Path) {
+        (**self).at_path(path)
+    }
+}
+
+/// An error, with the path at which it happened.
+#[derive(Debug)]
+pub struct PathError<E> {
+    pub path: Path,
+    pub error: E,
+}
+
+impl<E> fmt::Display for PathError<E> where E: fmt::Display {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.error, self.path)
+    }
+}
+
+impl<E> error::Error for PathError<E> where E: error::Error {}
+
+// A container being read.
+#[derive(Debug)]
+struct Frame {
+    array: bool,
+    // Number of elements or members started so far.
+    count: usize,
+}
+
+/// Keeps track of the path of the current value from the events of a
+/// sink. Shared by the sink adapters that need the path.
+///
+/// Values are started with `begin_value`, and ended with `end_value`, which
+/// is also how a skipped value is left. Keys are read from the string
+/// events.
+#[derive(Debug, Default)]
+pub struct PathTracker {
+    path: Path,
+    frames: Vec<Frame>,
+    // Set once the current value has been started.
+    started: bool,
+    key: Vec<u8>,
+    in_key: bool,
+    // Cleared when a part of the key being read was not in the window.
+    key_complete: bool,
+}
+
+impl PathTracker {
+    pub fn new() -> PathTracker {
+        PathTracker::default()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of containers the current value is in.
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The index of each value on the path in its container, counting the
+    /// members of objects in order. Values sort in document order by these.
+    pub fn indices(&self) -> Vec<usize> {
+        self.frames.iter().map(|frame| frame.count - 1).collect()
+    }
+
+    /// The last segment of the path, for a value that just started. None at
+    /// the root, or if its key could not be read in full.
+    pub fn segment(&self) -> Option<&Segment> {
+        match self.frames.last() {
+            Some(frame) if !frame.array && !self.key_complete => None,
+            Some(_) => self.path.segments.last(),
+            None => None,
+        }
+    }
+
+    /// Starts a value. Adds its segment if it is in an array, the segment
+    /// of a member value is added with its key. Returns false if the value
+    /// was started already.
+    pub fn begin_value(&mut self) -> bool {
+        if mem::replace(&mut self.started, true) {
+            return false;
+        }
+        if let Some(frame) = self.frames.last_mut() {
+            if frame.array {
+                self.path.segments.push(Segment::Index(frame.count));
+                frame.count += 1;
+            }
+        }
+        true
+    }
+
+    /// Ends or skips the current value, removing its segment.
+    pub fn end_value(&mut self) {
+        self.started = false;
+        if !self.frames.is_empty() {
+            self.path.segments.pop();
+        }
+    }
+
+    /// Enters the container that was just started.
+    pub fn push_container(&mut self, array: bool) {
+        self.started = false;
+        self.frames.push(Frame {
+            array,
+            count: 0,
+        });
+    }
+
+    /// Leaves the current container, which is then ended like any value.
+    pub fn pop_container(&mut self) {
+        self.frames.pop();
+    }
+
+    pub fn start_string(&mut self, pos: StringPosition) {
+        self.in_key = pos == StringPosition::MapKey;
+        if self.in_key {
+            self.key.clear();
+            self.key_complete = true;
+        }
+    }
+
+    pub fn append_string_range(&mut self) {
+        // Keys can not be resolved without the window.
+        if self.in_key {
+            self.key_complete = false;
+        }
+    }
+
+    pub fn append_string_window(&mut self, string: Range, window: Window) {
+        if self.in_key {
+            match window.get(string) {
+                Some(bytes) => self.key.extend_from_slice(bytes),
+                None => self.key_complete = false,
+            }
+        }
+    }
+
+    pub fn append_string_single(&mut self, character: u8) {
+        if self.in_key {
+            self.key.push(character);
+        }
+    }
+
+    pub fn append_string_codepoint(&mut self, codepoint: char) {
+        if self.in_key {
+            let mut buf = [0; 4];
+            self.key.extend_from_slice(codepoint.encode_utf8(&mut buf).as_bytes());
+        }
+    }
+
+    /// Adds the key to the path, if the string was one.
+    pub fn finalize_string(&mut self, pos: StringPosition) {
+        if pos == StringPosition::MapKey {
+            self.in_key = false;
+            // The tokenizer only passes on valid UTF-8.
+            let key = String::from_utf8_lossy(&self.key).into_owned();
+            self.path.segments.push(Segment::Key(key));
+            if let Some(frame) = self.frames.last_mut() {
+                frame.count += 1;
+            }
+        }
+    }
+}
+
+/// Sink adapter keeping track of the path of the current value, so that
+/// sinks do not have to.
+///
+/// The inner sink is told the path through `PathSink::at_path` before each
+/// event. When parsing fails, `attach` adds the path reached to the error.
+///
+/// Parts of keys passed as a `Range` without the window are left out of
+/// the path. `SourceSink` always passes the window.
+#[derive(Debug)]
+pub struct PathTracking<S> {
+    inner: S,
+    tracker: PathTracker,
+    // Set when the inner sink bailed at the end of a value. The path stays
+    // at the value until the next event.
+    ended: bool,
+}
+
+impl<S> PathTracking<S> where S: PathSink {
+    pub fn new(inner: S) -> PathTracking<S> {
+        PathTracking {
+            inner,
+            tracker: PathTracker::new(),
+            ended: false,
+        }
+    }
+
+    /// The path of the value being read. After an error, the path of the
+    /// innermost value that was being read.
+    pub fn path(&self) -> &Path {
+        self.tracker.path()
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Adds the current path to an error.
+    pub fn attach<E>(&self, error: E) -> PathError<E> {
+        PathError {
+            path: self.path().clone(),
+            error,
+        }
+    }
+
+    // Finishes a value the inner sink bailed on.
+    fn settle(&mut self) {
+        if mem::replace(&mut self.ended, false) {
+            self.tracker.end_value();
+        }
+    }
+
+    // Ends a value, unless the inner sink bailed on it.
+    fn end_value<B>(&mut self, result: Result<(), B>) -> Result<(), B> {
+        match result {
+            Ok(()) => self.tracker.end_value(),
+            Err(_) => self.ended = true,
+        }
+        result
+    }
+
+    fn scalar<F>(&mut self, push: F) -> Result<(), S::Bail>
+        where F: FnOnce(&mut S) -> Result<(), S::Bail>
+    {
+        self.settle();
+        self.tracker.begin_value();
+        self.inner.at_path(self.tracker.path());
+        let result = push(&mut self.inner);
+        self.end_value(result)
+    }
+
+    fn push_container(&mut self, array: bool) {
+        self.settle();
+        self.tracker.begin_value();
+        self.inner.at_path(self.tracker.path());
+        self.tracker.push_container(array);
+    }
+}
+
+impl<S> Bailable for PathTracking<S> where S: PathSink {
+    type Bail = S::Bail;
+}
+
+impl<S> Sink for PathTracking<S> where S: PathSink {
+    fn push_map(&mut self, pos: Position) {
+        self.push_container(false);
+        self.inner.push_map(pos);
+    }
+    fn push_array(&mut self, pos: Position) {
+        self.push_container(true);
+        self.inner.push_array(pos);
+    }
+
+    fn push_number(&mut self, pos: Position, number: NumberData) -> Result<(), Self::Bail> {
+        self.scalar(|inner| inner.push_number(pos, number))
+    }
+    fn push_number_window(&mut self, pos: Position, number: NumberData, window: Window) -> Result<(), Self::Bail> {
+        self.scalar(|inner| inner.push_number_window(pos, number, window))
+    }
+    fn push_bool(&mut self, pos: Position, boolean: bool) -> Result<(), Self::Bail> {
+        self.scalar(|inner| inner.push_bool(pos, boolean))
+    }
+    fn push_null(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        self.scalar(|inner| inner.push_null(pos))
+    }
+
+    fn start_string(&mut self, pos: StringPosition) {
+        self.start_string_kind(pos, StringKind::DoubleQuoted)
+    }
+    fn start_string_kind(&mut self, pos: StringPosition, kind: StringKind) {
+        self.settle();
+        if pos != StringPosition::MapKey {
+            self.tracker.begin_value();
+        }
+        self.tracker.start_string(pos);
+        self.inner.at_path(self.tracker.path());
+        self.inner.start_string_kind(pos, kind)
+    }
+    fn append_string_range(&mut self, string: Range) {
+        self.tracker.append_string_range();
+        self.inner.at_path(self.tracker.path());
+        self.inner.append_string_range(string)
+    }
+    fn append_string_window(&mut self, string: Range, window: Window) {
+        self.tracker.append_string_window(string, window);
+        self.inner.at_path(self.tracker.path());
+        self.inner.append_string_window(string, window)
+    }
+    fn append_string_single(&mut self, character: u8) {
+        self.tracker.append_string_single(character);
+        self.inner.at_path(self.tracker.path());
+        self.inner.append_string_single(character)
+    }
+    fn append_string_codepoint(&mut self, codepoint: char) {
+        self.tracker.append_string_codepoint(codepoint);
+        self.inner.at_path(self.tracker.path());
+        self.inner.append_string_codepoint(codepoint)
+    }
+    fn finalize_string(&mut self, pos: StringPosition) -> Result<(), Self::Bail> {
+        self.inner.at_path(self.tracker.path());
+        let result = self.inner.finalize_string(pos);
+        if pos != StringPosition::MapKey {
+            return self.end_value(result);
+        }
+        self.tracker.finalize_string(pos);
+        result
+    }
+
+    fn finalize_array(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        self.settle();
+        self.tracker.pop_container();
+        self.inner.at_path(self.tracker.path());
+        let result = self.inner.finalize_array(pos);
+        self.end_value(result)
+    }
+    fn finalize_map(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        self.settle();
+        self.tracker.pop_container();
+        self.inner.at_path(self.tracker.path());
+        let result = self.inner.finalize_map(pos);
+        self.end_value(result)
+    }
+
+    fn pop_into_map(&mut self) {
+        self.settle();
+        self.inner.at_path(self.tracker.path());
+        self.inner.pop_into_map()
+    }
+    fn pop_into_array(&mut self) {
+        self.settle();
+        self.inner.at_path(self.tracker.path());
+        self.inner.pop_into_array()
+    }
+
+    fn skip_value(&mut self, pos: Position) -> bool {
+        self.settle();
+        self.tracker.begin_value();
+        self.inner.at_path(self.tracker.path());
+        if self.inner.skip_value(pos) {
+            self.tracker.end_value();
+            return true;
+        }
+        false
+    }
+
+    fn comment(&mut self, comment: Range) {
+        self.inner.comment(comment)
+    }
+    fn comment_window(&mut self, comment: Range, window: Window) {
+        self.inner.comment_window(comment, window)
+    }
+
+    fn start_document(&mut self, start: Pos) {
+        self.tracker = PathTracker::new();
+        self.ended = false;
+        self.inner.start_document(start)
+    }
+    fn end_document(&mut self, document: Range) -> Result<(), Self::Bail> {
+        self.inner.end_document(document)
+    }
+    fn document_error(&mut self, document: Range, pos: Pos, reason: Unexpected) -> Result<(), Self::Bail> {
+        self.inner.document_error(document, pos, reason)
+    }
+}
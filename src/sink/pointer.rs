@@ -0,0 +1,392 @@
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use ::Bailable;
+use super::{Sink, NumberData, Position, StringPosition, StringKind};
+use super::path::{PathTracker, Segment};
+use ::input::{Range, Window};
+
+/// A JSON Pointer, as in RFC 6901.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pointer {
+    tokens: Vec<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PointerError {
+    /// A non-empty pointer has to start with `/`.
+    MissingSlash,
+    /// A `~` not followed by `0` or `1`.
+    InvalidEscape,
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match *self {
+            PointerError::MissingSlash => "pointer does not start with '/'",
+            PointerError::InvalidEscape => "invalid escape in pointer",
+        };
+        f.write_str(description)
+    }
+}
+
+impl error::Error for PointerError {}
+
+impl Pointer {
+    /// The pointer to the whole document.
+    pub fn root() -> Pointer {
+        Pointer {
+            tokens: Vec::new(),
+        }
+    }
+
+    /// The pointer made of the given unescaped reference tokens.
+    pub fn from_tokens(tokens: Vec<String>) -> Pointer {
+        Pointer {
+            tokens,
+        }
+    }
+
+    /// Parses a pointer like `/data/items/3/id`, with `~0` and `~1` standing
+    /// for `~` and `/`. The empty string points to the whole document.
+    pub fn parse(pointer: &str) -> Result<Pointer, PointerError> {
+        if pointer.is_empty() {
+            return Ok(Pointer::root());
+        }
+        if !pointer.starts_with('/') {
+            return Err(PointerError::MissingSlash);
+        }
+        let tokens = pointer[1..].split('/').map(|token| {
+            let mut unescaped = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(character) = chars.next() {
+                unescaped.push(match character {
+                    '~' => match chars.next() {
+                        Some('0') => '~',
+                        Some('1') => '/',
+                        _ => return Err(PointerError::InvalidEscape),
+                    },
+                    character => character,
+                });
+            }
+            Ok(unescaped)
+        }).collect::<Result<_, _>>()?;
+        Ok(Pointer {
+            tokens,
+        })
+    }
+
+    /// The unescaped reference tokens.
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    // The array index a token refers to. Leading zeros are not allowed,
+    // and `-` never refers to an existing element.
+    fn index(token: &str) -> Option<usize> {
+        if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+            return None;
+        }
+        if !token.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        token.parse().ok()
+    }
+}
+
+impl FromStr for Pointer {
+    type Err = PointerError;
+
+    fn from_str(pointer: &str) -> Result<Pointer, PointerError> {
+        Pointer::parse(pointer)
+    }
+}
+
+/// Formats the pointer in its escaped form.
+impl fmt::Display for Pointer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointerBail<B> {
+    /// The target value has been passed on in full. Nothing after it is
+    /// passed on, so parsing can stop.
+    Done,
+    /// The inner sink bailed.
+    Inner(B),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Target {
+    Before,
+    // In the target, with the number of containers opened in it.
+    Inside(usize),
+    Done,
+}
+
+/// Sink adapter passing on only the value a `Pointer` refers to.
+///
+/// Tracks the path of the current value, and asks for every value not on
+/// the way to the target to be skipped. The target is passed on to the
+/// inner sink as if it was the whole document, after which the sink bails
+/// with `PointerBail::Done`, or with the inner sink's bail if it bailed on
+/// the target's last event. If the document ends without `found` becoming
+/// true, the target does not exist.
+#[derive(Debug)]
+pub struct PointerSink<S> {
+    inner: S,
+    tokens: Vec<String>,
+    indices: Vec<Option<usize>>,
+    // Path of the current value, while on the way to the target.
+    tracker: PathTracker,
+    target: Target,
+}
+
+impl<S> PointerSink<S> where S: Sink {
+    pub fn new(pointer: &Pointer, inner: S) -> PointerSink<S> {
+        PointerSink {
+            inner,
+            tokens: pointer.tokens.clone(),
+            indices: pointer.tokens.iter().map(|token| Pointer::index(token)).collect(),
+            tracker: PathTracker::new(),
+            target: Target::Before,
+        }
+    }
+
+    /// Whether the target has been passed on in full.
+    pub fn found(&self) -> bool {
+        self.target == Target::Done
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    // Called once when a value starts outside the target. Returns whether
+    // it is on the way to the target, or the target itself.
+    fn value_matches(&mut self) -> bool {
+        self.tracker.begin_value();
+        let depth = self.tracker.depth();
+        let matches = depth == 0 || match self.tracker.segment() {
+            Some(&Segment::Index(index)) => self.indices[depth - 1] == Some(index),
+            Some(Segment::Key(key)) => *key == self.tokens[depth - 1],
+            None => false,
+        };
+        if matches && depth == self.tokens.len() {
+            self.target = Target::Inside(0);
+        }
+        matches
+    }
+
+    // The position to pass on for a value in the target. The target itself
+    // is passed on as the root.
+    fn position(&self, pos: Position) -> Position {
+        if self.target == Target::Inside(0) { Position::Root } else { pos }
+    }
+
+    // Passes on a scalar, which may be the target itself.
+    fn scalar<F>(&mut self, pos: Position, push: F) -> Result<(), PointerBail<S::Bail>>
+        where F: FnOnce(&mut S, Position) -> Result<(), S::Bail>
+    {
+        if self.target == Target::Before {
+            self.value_matches();
+        }
+        match self.target {
+            Target::Inside(_) => {
+                let pos = self.position(pos);
+                let result = push(&mut self.inner, pos);
+                self.end_value(result)
+            },
+            // Not the target, which can not be in a scalar.
+            Target::Before => {
+                self.tracker.end_value();
+                Ok(())
+            },
+            Target::Done => Ok(()),
+        }
+    }
+
+    // Called after a value in the target ended, with what the inner sink
+    // returned for its last event. The parser does not repeat that event
+    // when resuming after a bail, so the target is marked done before
+    // passing on a bail of the inner sink.
+    fn end_value(&mut self, result: Result<(), S::Bail>) -> Result<(), PointerBail<S::Bail>> {
+        let done = self.target == Target::Inside(0);
+        if done {
+            self.target = Target::Done;
+        }
+        result.map_err(PointerBail::Inner)?;
+        if done {
+            return Err(PointerBail::Done);
+        }
+        Ok(())
+    }
+}
+
+impl<S> Bailable for PointerSink<S> where S: Sink {
+    type Bail = PointerBail<S::Bail>;
+}
+
+impl<S> Sink for PointerSink<S> where S: Sink {
+    fn push_map(&mut self, pos: Position) {
+        match self.target {
+            Target::Inside(depth) => {
+                let pos = self.position(pos);
+                self.inner.push_map(pos);
+                self.target = Target::Inside(depth + 1);
+            },
+            Target::Before => self.tracker.push_container(false),
+            Target::Done => (),
+        }
+    }
+    fn push_array(&mut self, pos: Position) {
+        match self.target {
+            Target::Inside(depth) => {
+                let pos = self.position(pos);
+                self.inner.push_array(pos);
+                self.target = Target::Inside(depth + 1);
+            },
+            Target::Before => self.tracker.push_container(true),
+            Target::Done => (),
+        }
+    }
+
+    fn push_number(&mut self, pos: Position, number: NumberData) -> Result<(), Self::Bail> {
+        self.scalar(pos, |inner, pos| inner.push_number(pos, number))
+    }
+    fn push_number_window(&mut self, pos: Position, number: NumberData, window: Window) -> Result<(), Self::Bail> {
+        self.scalar(pos, |inner, pos| inner.push_number_window(pos, number, window))
+    }
+    fn push_bool(&mut self, pos: Position, boolean: bool) -> Result<(), Self::Bail> {
+        self.scalar(pos, |inner, pos| inner.push_bool(pos, boolean))
+    }
+    fn push_null(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        self.scalar(pos, |inner, pos| inner.push_null(pos))
+    }
+
+    fn start_string(&mut self, pos: StringPosition) {
+        self.start_string_kind(pos, StringKind::DoubleQuoted)
+    }
+    fn start_string_kind(&mut self, pos: StringPosition, kind: StringKind) {
+        match self.target {
+            Target::Inside(0) => self.inner.start_string_kind(StringPosition::Root, kind),
+            Target::Inside(_) => self.inner.start_string_kind(pos, kind),
+            Target::Before => self.tracker.start_string(pos),
+            Target::Done => (),
+        }
+    }
+    fn append_string_range(&mut self, string: Range) {
+        match self.target {
+            Target::Inside(_) => self.inner.append_string_range(string),
+            Target::Before => self.tracker.append_string_range(),
+            Target::Done => (),
+        }
+    }
+    fn append_string_window(&mut self, string: Range, window: Window) {
+        match self.target {
+            Target::Inside(_) => self.inner.append_string_window(string, window),
+            Target::Before => self.tracker.append_string_window(string, window),
+            Target::Done => (),
+        }
+    }
+    fn append_string_single(&mut self, character: u8) {
+        match self.target {
+            Target::Inside(_) => self.inner.append_string_single(character),
+            Target::Before => self.tracker.append_string_single(character),
+            Target::Done => (),
+        }
+    }
+    fn append_string_codepoint(&mut self, codepoint: char) {
+        match self.target {
+            Target::Inside(_) => self.inner.append_string_codepoint(codepoint),
+            Target::Before => self.tracker.append_string_codepoint(codepoint),
+            Target::Done => (),
+        }
+    }
+    fn finalize_string(&mut self, pos: StringPosition) -> Result<(), Self::Bail> {
+        match self.target {
+            Target::Inside(0) => {
+                let result = self.inner.finalize_string(StringPosition::Root);
+                self.end_value(result)
+            },
+            Target::Inside(_) => self.inner.finalize_string(pos).map_err(PointerBail::Inner),
+            Target::Before => {
+                self.tracker.finalize_string(pos);
+                Ok(())
+            },
+            Target::Done => Ok(()),
+        }
+    }
+
+    fn finalize_array(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        match self.target {
+            Target::Inside(depth) => {
+                self.target = Target::Inside(depth - 1);
+                let pos = self.position(pos);
+                let result = self.inner.finalize_array(pos);
+                self.end_value(result)
+            },
+            Target::Before => {
+                self.tracker.pop_container();
+                self.tracker.end_value();
+                Ok(())
+            },
+            Target::Done => Ok(()),
+        }
+    }
+    fn finalize_map(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        match self.target {
+            Target::Inside(depth) => {
+                self.target = Target::Inside(depth - 1);
+                let pos = self.position(pos);
+                let result = self.inner.finalize_map(pos);
+                self.end_value(result)
+            },
+            Target::Before => {
+                self.tracker.pop_container();
+                self.tracker.end_value();
+                Ok(())
+            },
+            Target::Done => Ok(()),
+        }
+    }
+
+    fn pop_into_map(&mut self) {
+        if let Target::Inside(depth) = self.target {
+            if depth > 0 {
+                self.inner.pop_into_map();
+            }
+        }
+    }
+    fn pop_into_array(&mut self) {
+        if let Target::Inside(depth) = self.target {
+            if depth > 0 {
+                self.inner.pop_into_array();
+            }
+        }
+    }
+
+    fn skip_value(&mut self, pos: Position) -> bool {
+        match self.target {
+            Target::Inside(_) => self.inner.skip_value(pos),
+            Target::Before => {
+                let skip = !self.value_matches();
+                if skip {
+                    self.tracker.end_value();
+                }
+                skip
+            },
+            Target::Done => true,
+        }
+    }
+}
@@ -0,0 +1,748 @@
+use std::cmp::Ordering;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use ::Bailable;
+use super::{Sink, NumberData, Position, StringPosition, StringKind};
+use super::value::{Value, ValueSink, ValueError};
+use super::path::{PathTracker, Segment};
+use ::input::{Range, Window};
+
+/// A query in a subset of JSONPath.
+///
+/// Supported are member names (`$.a`, `$['a']`), wildcards (`$.*`,
+/// `$[*]`), array indices (`$[3]`), slices (`$[1:5:2]`, without negative
+/// bounds), descendants (`$..a`, `$..*`, `$..[0]`) and filters comparing a
+/// member to a literal (`$[?(@.x == 1)]`, `$[?(@.x)]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// Invalid syntax at the given byte offset.
+    Syntax(usize),
+    /// Valid JSONPath not supported for streaming, such as negative
+    /// indices, at the given byte offset.
+    Unsupported(usize),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            QueryError::Syntax(offset) => write!(f, "invalid query syntax at byte {}", offset),
+            QueryError::Unsupported(offset) => write!(f, "unsupported query at byte {}", offset),
+        }
+    }
+}
+
+impl error::Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    // Whether the selector applies to all descendants, not only children.
+    descendant: bool,
+    selector: Selector,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(usize),
+    Slice {
+        start: usize,
+        end: Option<usize>,
+        step: usize,
+    },
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    path: Vec<Member>,
+    // Without a comparison, the filter checks that the member exists.
+    comparison: Option<(Operator, Literal)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Member {
+    Name(String),
+    Index(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+// Where a value is in its container.
+#[derive(Debug, Copy, Clone)]
+enum Child<'a> {
+    // None if the key could not be read.
+    Key(Option<&'a str>),
+    Index(usize),
+}
+
+impl Selector {
+    // Whether a child matches, for all selectors but filters.
+    fn matches(&self, child: Child) -> bool {
+        match (self, child) {
+            (&Selector::Wildcard, _) => true,
+            (Selector::Name(name), Child::Key(key)) => key == Some(&name[..]),
+            (&Selector::Index(index), Child::Index(i)) => i == index,
+            (&Selector::Slice { start, end, step }, Child::Index(i)) => {
+                i >= start && end.is_none_or(|end| i < end) && (i - start) % step == 0
+            },
+            _ => false,
+        }
+    }
+}
+
+impl Filter {
+    // A missing member only satisfies `!=`.
+    fn matches(&self, value: &Value) -> bool {
+        let member = self.path.iter().try_fold(value, |member, part| match *part {
+            Member::Name(ref name) => member.get(name),
+            Member::Index(index) => member.get_at(index),
+        });
+        let (operator, literal) = match self.comparison {
+            Some((operator, ref literal)) => (operator, literal),
+            None => return member.is_some(),
+        };
+        let ordering = match (member, literal) {
+            (Some(&Value::Null), &Literal::Null) => Some(Ordering::Equal),
+            (Some(&Value::Bool(a)), &Literal::Bool(b)) if a == b => Some(Ordering::Equal),
+            (Some(&Value::Number(a)), &Literal::Number(b)) => a.as_f64().partial_cmp(&b),
+            (Some(Value::String(a)), Literal::String(b)) => Some(a[..].cmp(&b[..])),
+            _ => None,
+        };
+        match operator {
+            Operator::Equal => ordering == Some(Ordering::Equal),
+            Operator::NotEqual => ordering != Some(Ordering::Equal),
+            Operator::Less => ordering == Some(Ordering::Less),
+            Operator::LessEqual => ordering.is_some_and(|ordering| ordering != Ordering::Greater),
+            Operator::Greater => ordering == Some(Ordering::Greater),
+            Operator::GreaterEqual => ordering.is_some_and(|ordering| ordering != Ordering::Less),
+        }
+    }
+}
+
+// The children of a value, with where they are.
+fn children(value: &Value) -> Vec<(Child<'_>, &Value)> {
+    match *value {
+        Value::Array(ref array) => array.iter().enumerate()
+            .map(|(index, child)| (Child::Index(index), child))
+            .collect(),
+        Value::Object(ref map) => map.iter()
+            .map(|(key, child)| (Child::Key(Some(&key[..])), child))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Adds the matches in `value` to `out`, with their indices as in
+// `PathTracker::indices`. `at` holds the indices of `value`.
+fn select<'v>(steps: &[Step], value: &'v Value, at: &mut Vec<usize>, out: &mut Vec<(Vec<usize>, &'v Value)>) {
+    let (step, rest) = match steps.split_first() {
+        Some(split) => split,
+        None => return out.push((at.clone(), value)),
+    };
+    for (index, (child, member)) in children(value).into_iter().enumerate() {
+        at.push(index);
+        let matches = match step.selector {
+            Selector::Filter(ref filter) => filter.matches(member),
+            ref selector => selector.matches(child),
+        };
+        if matches {
+            select(rest, member, at, out);
+        }
+        if step.descendant {
+            select(steps, member, at, out);
+        }
+        at.pop();
+    }
+}
+
+// Sorts matches into document order, keeping each value once.
+fn document_order<T>(matches: &mut Vec<(Vec<usize>, T)>) {
+    matches.sort_by(|a, b| a.0.cmp(&b.0));
+    matches.dedup_by(|a, b| a.0 == b.0);
+}
+
+impl Query {
+    /// Parses a query, which has to start with `$`.
+    pub fn parse(query: &str) -> Result<Query, QueryError> {
+        let mut parser = QueryParser {
+            input: query.as_bytes(),
+            offset: 0,
+        };
+        parser.expect(b'$')?;
+        let mut steps = Vec::new();
+        while parser.offset < parser.input.len() {
+            steps.push(parser.step()?);
+        }
+        Ok(Query {
+            steps,
+        })
+    }
+
+    /// Evaluates the query against a value in memory, giving the matches in
+    /// document order. A value matched in several ways is given once.
+    pub fn select<'v>(&self, value: &'v Value) -> Vec<&'v Value> {
+        let mut matches = Vec::new();
+        select(&self.steps, value, &mut Vec::new(), &mut matches);
+        document_order(&mut matches);
+        matches.into_iter().map(|(_, value)| value).collect()
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(query: &str) -> Result<Query, QueryError> {
+        Query::parse(query)
+    }
+}
+
+struct QueryParser<'a> {
+    input: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> QueryParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.offset).cloned()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.offset += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), QueryError> {
+        if self.eat(byte) {
+            Ok(())
+        } else {
+            Err(QueryError::Syntax(self.offset))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek() == Some(b' ') {
+            self.offset += 1;
+        }
+    }
+
+    fn step(&mut self) -> Result<Step, QueryError> {
+        if self.eat(b'.') {
+            let descendant = self.eat(b'.');
+            let selector = match self.peek() {
+                Some(b'[') if descendant => self.bracket()?,
+                Some(b'*') => {
+                    self.offset += 1;
+                    Selector::Wildcard
+                },
+                _ => Selector::Name(self.name()?),
+            };
+            Ok(Step {
+                descendant,
+                selector,
+            })
+        } else if self.peek() == Some(b'[') {
+            Ok(Step {
+                descendant: false,
+                selector: self.bracket()?,
+            })
+        } else {
+            Err(QueryError::Syntax(self.offset))
+        }
+    }
+
+    // An unquoted member name, up to the next special character.
+    fn name(&mut self) -> Result<String, QueryError> {
+        let start = self.offset;
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'.' | b'[' | b']' | b'(' | b')' | b' ' | b'=' | b'!' | b'<' | b'>' => break,
+                _ => self.offset += 1,
+            }
+        }
+        if self.offset == start {
+            return Err(QueryError::Syntax(start));
+        }
+        // Only split at ASCII bytes, so this stays valid UTF-8.
+        Ok(String::from_utf8(self.input[start..self.offset].to_vec()).unwrap())
+    }
+
+    fn bracket(&mut self) -> Result<Selector, QueryError> {
+        self.expect(b'[')?;
+        self.skip_whitespace();
+        let selector = match self.peek() {
+            Some(b'*') => {
+                self.offset += 1;
+                Selector::Wildcard
+            },
+            Some(b'\'') | Some(b'"') => Selector::Name(self.quoted()?),
+            Some(b'?') => {
+                self.offset += 1;
+                self.expect(b'(')?;
+                let filter = self.filter()?;
+                self.expect(b')')?;
+                Selector::Filter(filter)
+            },
+            _ => self.slice()?,
+        };
+        self.skip_whitespace();
+        self.expect(b']')?;
+        Ok(selector)
+    }
+
+    fn quoted(&mut self) -> Result<String, QueryError> {
+        let start = self.offset;
+        let quote = self.peek().unwrap();
+        self.offset += 1;
+        let mut bytes = Vec::new();
+        loop {
+            match self.peek() {
+                Some(byte) if byte == quote => break,
+                Some(b'\\') => {
+                    self.offset += 1;
+                    match self.peek() {
+                        Some(byte) => bytes.push(byte),
+                        None => return Err(QueryError::Syntax(self.offset)),
+                    }
+                },
+                Some(byte) => bytes.push(byte),
+                None => return Err(QueryError::Syntax(start)),
+            }
+            self.offset += 1;
+        }
+        self.offset += 1;
+        String::from_utf8(bytes).map_err(|_| QueryError::Syntax(start))
+    }
+
+    fn index(&mut self) -> Result<Option<usize>, QueryError> {
+        let start = self.offset;
+        if self.peek() == Some(b'-') {
+            return Err(QueryError::Unsupported(start));
+        }
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+            self.offset += 1;
+        }
+        if self.offset == start {
+            return Ok(None);
+        }
+        let digits = ::std::str::from_utf8(&self.input[start..self.offset]).unwrap();
+        digits.parse().map(Some).map_err(|_| QueryError::Unsupported(start))
+    }
+
+    fn slice(&mut self) -> Result<Selector, QueryError> {
+        let start_offset = self.offset;
+        let start = self.index()?;
+        if !self.eat(b':') {
+            return match start {
+                Some(index) => Ok(Selector::Index(index)),
+                None => Err(QueryError::Syntax(start_offset)),
+            };
+        }
+        let end = self.index()?;
+        let step = if self.eat(b':') { self.index()? } else { None };
+        match step {
+            Some(0) => Err(QueryError::Syntax(start_offset)),
+            step => Ok(Selector::Slice {
+                start: start.unwrap_or(0),
+                end,
+                step: step.unwrap_or(1),
+            }),
+        }
+    }
+
+    fn filter(&mut self) -> Result<Filter, QueryError> {
+        self.skip_whitespace();
+        self.expect(b'@')?;
+        let mut path = Vec::new();
+        loop {
+            if self.eat(b'.') {
+                path.push(Member::Name(self.name()?));
+            } else if self.eat(b'[') {
+                path.push(match self.peek() {
+                    Some(b'\'') | Some(b'"') => Member::Name(self.quoted()?),
+                    _ => match self.index()? {
+                        Some(index) => Member::Index(index),
+                        None => return Err(QueryError::Syntax(self.offset)),
+                    },
+                });
+                self.expect(b']')?;
+            } else {
+                break;
+            }
+        }
+        self.skip_whitespace();
+        let operator = match (self.peek(), self.input.get(self.offset + 1).cloned()) {
+            (Some(b'='), Some(b'=')) => Operator::Equal,
+            (Some(b'!'), Some(b'=')) => Operator::NotEqual,
+            (Some(b'<'), Some(b'=')) => Operator::LessEqual,
+            (Some(b'>'), Some(b'=')) => Operator::GreaterEqual,
+            (Some(b'<'), _) => Operator::Less,
+            (Some(b'>'), _) => Operator::Greater,
+            _ => return Ok(Filter {
+                path,
+                comparison: None,
+            }),
+        };
+        self.offset += match operator {
+            Operator::Less | Operator::Greater => 1,
+            _ => 2,
+        };
+        self.skip_whitespace();
+        let literal = self.literal()?;
+        self.skip_whitespace();
+        Ok(Filter {
+            path,
+            comparison: Some((operator, literal)),
+        })
+    }
+
+    fn literal(&mut self) -> Result<Literal, QueryError> {
+        if let Some(b'\'') | Some(b'"') = self.peek() {
+            return Ok(Literal::String(self.quoted()?));
+        }
+        let start = self.offset;
+        while self.peek().is_some_and(|byte| byte.is_ascii_alphanumeric() || b"+-.".contains(&byte)) {
+            self.offset += 1;
+        }
+        let word = ::std::str::from_utf8(&self.input[start..self.offset]).unwrap();
+        match word {
+            "null" => Ok(Literal::Null),
+            "true" => Ok(Literal::Bool(true)),
+            "false" => Ok(Literal::Bool(false)),
+            _ => word.parse().map(Literal::Number).map_err(|_| QueryError::Syntax(start)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryBail<B> {
+    /// A matching value could not be read.
+    Value(ValueError),
+    /// The callback bailed.
+    Callback(B),
+}
+
+// A value being built, because it matches, or to check it against a
+// filter.
+#[derive(Debug)]
+struct Builder {
+    sink: ValueSink,
+    // Number of containers the value is in.
+    depth: usize,
+    // Indices of the value, see `PathTracker::indices`.
+    at: Vec<usize>,
+    // The step of the filter to check.
+    filter: Option<usize>,
+}
+
+/// Sink evaluating a `Query` while parsing, passing each matching value to
+/// a callback.
+///
+/// Only matching values are built, everything else is skipped. Matches are
+/// passed on in document order, as by `Query::select`: a value is passed on
+/// as soon as it is complete, except that matches inside another match wait
+/// for it. A value with a filter step ahead of it is built in full, and the
+/// rest of the query is evaluated on it in memory.
+#[derive(Debug)]
+pub struct QuerySink<F> {
+    steps: Vec<Step>,
+    callback: F,
+    tracker: PathTracker,
+    // The states of the query are the number of steps matched so far. The
+    // states that reached each container being read.
+    active: Vec<Vec<usize>>,
+    builders: Vec<Builder>,
+    // Complete matches waiting for the matches around them.
+    matches: Vec<(Vec<usize>, Value)>,
+    // States of the container or string about to start.
+    pending: Vec<usize>,
+}
+
+impl<F, B> QuerySink<F> where F: FnMut(Value) -> Result<(), B> {
+    pub fn new(query: &Query, callback: F) -> QuerySink<F> {
+        QuerySink {
+            steps: query.steps.clone(),
+            callback,
+            tracker: PathTracker::new(),
+            active: Vec::new(),
+            builders: Vec::new(),
+            matches: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn into_callback(self) -> F {
+        self.callback
+    }
+
+    // Called once when a value starts. Starts building it if needed, and
+    // returns the states reaching it.
+    fn begin_value(&mut self) -> Vec<usize> {
+        self.tracker.begin_value();
+        let depth = self.tracker.depth();
+        let end = self.steps.len();
+        let mut active = Vec::new();
+        let mut filters = Vec::new();
+        match self.active.last() {
+            None => active.push(0),
+            Some(states) => {
+                let child = match self.tracker.segment() {
+                    Some(&Segment::Index(index)) => Child::Index(index),
+                    Some(Segment::Key(key)) => Child::Key(Some(key)),
+                    None => Child::Key(None),
+                };
+                for &state in states {
+                    let step = &self.steps[state];
+                    if step.descendant && !active.contains(&state) {
+                        active.push(state);
+                    }
+                    match step.selector {
+                        Selector::Filter(_) => filters.push(state),
+                        ref selector if selector.matches(child) && !active.contains(&(state + 1)) => {
+                            active.push(state + 1);
+                        },
+                        _ => (),
+                    }
+                }
+            },
+        }
+        if active.contains(&end) {
+            self.builders.push(Builder {
+                sink: ValueSink::new(),
+                depth,
+                at: self.tracker.indices(),
+                filter: None,
+            });
+        }
+        for state in filters {
+            self.builders.push(Builder {
+                sink: ValueSink::new(),
+                depth,
+                at: self.tracker.indices(),
+                filter: Some(state),
+            });
+        }
+        active.retain(|&state| state < end);
+        active
+    }
+
+    // Passes an event on to the builders containing the current value. The
+    // value a builder starts with is passed on as the root.
+    fn forward<G>(&mut self, mut event: G) -> Result<(), QueryBail<B>>
+        where G: FnMut(&mut ValueSink, bool) -> Result<(), ValueError>
+    {
+        let depth = self.tracker.depth();
+        for builder in &mut self.builders {
+            event(&mut builder.sink, builder.depth == depth).map_err(QueryBail::Value)?;
+        }
+        Ok(())
+    }
+
+    // Collects the matches of the builders whose value just ended, and
+    // passes them on once no match around them is being read.
+    fn complete(&mut self) -> Result<(), QueryBail<B>> {
+        let depth = self.tracker.depth();
+        while self.builders.last().is_some_and(|builder| builder.depth == depth) {
+            let builder = self.builders.pop().unwrap();
+            // Builders only see complete values.
+            let value = builder.sink.into_value().unwrap();
+            match builder.filter {
+                None => self.matches.push((builder.at, value)),
+                Some(state) => {
+                    let filter = match self.steps[state].selector {
+                        Selector::Filter(ref filter) => filter,
+                        _ => unreachable!(),
+                    };
+                    if !filter.matches(&value) {
+                        continue;
+                    }
+                    let mut at = builder.at;
+                    let mut matches = Vec::new();
+                    select(&self.steps[state + 1..], &value, &mut at, &mut matches);
+                    self.matches.extend(matches.into_iter().map(|(at, value)| (at, value.clone())));
+                },
+            }
+        }
+        if !self.builders.is_empty() {
+            return Ok(());
+        }
+        document_order(&mut self.matches);
+        self.matches.reverse();
+        while let Some((_, value)) = self.matches.pop() {
+            (self.callback)(value).map_err(QueryBail::Callback)?;
+        }
+        Ok(())
+    }
+
+    fn scalar<G>(&mut self, push: G) -> Result<(), QueryBail<B>>
+        where G: FnMut(&mut ValueSink, bool) -> Result<(), ValueError>
+    {
+        self.begin_value();
+        self.forward(push)?;
+        self.tracker.end_value();
+        self.complete()
+    }
+
+    fn push_container(&mut self, array: bool) {
+        let active = ::std::mem::take(&mut self.pending);
+        self.active.push(active);
+        self.tracker.push_container(array);
+    }
+}
+
+fn root(pos: Position, at_root: bool) -> Position {
+    if at_root { Position::Root } else { pos }
+}
+
+fn string_root(pos: StringPosition, at_root: bool) -> StringPosition {
+    if at_root { StringPosition::Root } else { pos }
+}
+
+impl<F, B> Bailable for QuerySink<F> where F: FnMut(Value) -> Result<(), B> {
+    type Bail = QueryBail<B>;
+}
+
+impl<F, B> Sink for QuerySink<F> where F: FnMut(Value) -> Result<(), B> {
+    fn push_map(&mut self, pos: Position) {
+        // `ValueSink` does not fail here.
+        let _ = self.forward(|sink, at_root| {
+            sink.push_map(root(pos, at_root));
+            Ok(())
+        });
+        self.push_container(false);
+    }
+    fn push_array(&mut self, pos: Position) {
+        let _ = self.forward(|sink, at_root| {
+            sink.push_array(root(pos, at_root));
+            Ok(())
+        });
+        self.push_container(true);
+    }
+
+    fn push_number(&mut self, pos: Position, number: NumberData) -> Result<(), Self::Bail> {
+        self.scalar(|sink, at_root| sink.push_number(root(pos, at_root), number.clone()))
+    }
+    fn push_number_window(&mut self, pos: Position, number: NumberData, window: Window) -> Result<(), Self::Bail> {
+        self.scalar(|sink, at_root| sink.push_number_window(root(pos, at_root), number.clone(), window))
+    }
+    fn push_bool(&mut self, pos: Position, boolean: bool) -> Result<(), Self::Bail> {
+        self.scalar(|sink, at_root| sink.push_bool(root(pos, at_root), boolean))
+    }
+    fn push_null(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        self.scalar(|sink, at_root| sink.push_null(root(pos, at_root)))
+    }
+
+    fn start_string(&mut self, pos: StringPosition) {
+        self.start_string_kind(pos, StringKind::DoubleQuoted)
+    }
+    fn start_string_kind(&mut self, pos: StringPosition, kind: StringKind) {
+        self.tracker.start_string(pos);
+        let _ = self.forward(|sink, at_root| {
+            sink.start_string_kind(string_root(pos, at_root), kind);
+            Ok(())
+        });
+    }
+    fn append_string_range(&mut self, string: Range) {
+        self.tracker.append_string_range();
+        let _ = self.forward(|sink, _| {
+            sink.append_string_range(string);
+            Ok(())
+        });
+    }
+    fn append_string_window(&mut self, string: Range, window: Window) {
+        self.tracker.append_string_window(string, window);
+        let _ = self.forward(|sink, _| {
+            sink.append_string_window(string, window);
+            Ok(())
+        });
+    }
+    fn append_string_single(&mut self, character: u8) {
+        self.tracker.append_string_single(character);
+        let _ = self.forward(|sink, _| {
+            sink.append_string_single(character);
+            Ok(())
+        });
+    }
+    fn append_string_codepoint(&mut self, codepoint: char) {
+        self.tracker.append_string_codepoint(codepoint);
+        let _ = self.forward(|sink, _| {
+            sink.append_string_codepoint(codepoint);
+            Ok(())
+        });
+    }
+    fn finalize_string(&mut self, pos: StringPosition) -> Result<(), Self::Bail> {
+        self.forward(|sink, at_root| sink.finalize_string(string_root(pos, at_root)))?;
+        if pos != StringPosition::MapKey {
+            self.pending.clear();
+            self.tracker.end_value();
+            return self.complete();
+        }
+        self.tracker.finalize_string(pos);
+        Ok(())
+    }
+
+    fn finalize_array(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        self.tracker.pop_container();
+        self.active.pop();
+        self.forward(|sink, at_root| sink.finalize_array(root(pos, at_root)))?;
+        self.tracker.end_value();
+        self.complete()
+    }
+    fn finalize_map(&mut self, pos: Position) -> Result<(), Self::Bail> {
+        self.tracker.pop_container();
+        self.active.pop();
+        self.forward(|sink, at_root| sink.finalize_map(root(pos, at_root)))?;
+        self.tracker.end_value();
+        self.complete()
+    }
+
+    fn pop_into_map(&mut self) {
+        // Only builders that contain the current container.
+        let depth = self.tracker.depth();
+        for builder in self.builders.iter_mut().filter(|builder| builder.depth < depth) {
+            builder.sink.pop_into_map();
+        }
+    }
+    fn pop_into_array(&mut self) {
+        let depth = self.tracker.depth();
+        for builder in self.builders.iter_mut().filter(|builder| builder.depth < depth) {
+            builder.sink.pop_into_array();
+        }
+    }
+
+    fn skip_value(&mut self, _pos: Position) -> bool {
+        self.pending = self.begin_value();
+        let skip = self.pending.is_empty() && self.builders.is_empty();
+        if skip {
+            self.tracker.end_value();
+        }
+        skip
+    }
+}
@@ -0,0 +1,406 @@
+//! A flat representation of a document, in the style of simdjson.
+//!
+//! The document is stored as a sequence of `u64` words, the tape. Each word
+//! has a tag in its top byte, and a payload in the rest:
+//!
+//! * `{` / `[` start an object or array. The payload is the index just past
+//!   the matching end, so a whole subtree can be skipped in O(1).
+//! * `}` / `]` end an object or array. The payload is the index of the
+//!   matching start.
+//! * `"` is a string. The payload is the offset of its bytes, with bit 55 set
+//!   if the offset is into the side buffer rather than the input. The next
+//!   word is the length.
+//! * `l`, `u` and `d` are an `i64`, `u64` and `f64`. The next word holds the
+//!   bits of the value.
+//! * `t`, `f` and `n` are `true`, `false` and `null`.
+//!
+//! Objects hold their members as a key string followed by the value.
+
+use std::mem;
+use std::str;
+
+use ::Bailable;
+use super::{Sink, NumberData, Position, StringPosition};
+use super::value::{Number, ValueError};
+use ::input::{Range, Window};
+
+const TAG_SHIFT: u32 = 56;
+const PAYLOAD_MASK: u64 = (1 << TAG_SHIFT) - 1;
+const SIDE_BUFFER: u64 = 1 << (TAG_SHIFT - 1);
+
+fn word(tag: u8, payload: u64) -> u64 {
+    ((tag as u64) << TAG_SHIFT) | (payload & PAYLOAD_MASK)
+}
+
+fn tag(word: u64) -> u8 {
+    (word >> TAG_SHIFT) as u8
+}
+
+fn payload(word: u64) -> u64 {
+    word & PAYLOAD_MASK
+}
+
+/// A document stored as a tape. Strings without escapes point into the
+/// input, all others into a side buffer.
+#[derive(Debug, Clone)]
+pub struct Tape<'a> {
+    words: Vec<u64>,
+    strings: Vec<u8>,
+    input: &'a [u8],
+}
+
+impl<'a> Tape<'a> {
+    /// The raw words of the tape.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// A cursor at the root value, if the tape holds a complete document.
+    pub fn root(&self) -> Option<Cursor<'_>> {
+        if self.words.is_empty() {
+            None
+        } else {
+            Some(Cursor { tape: self, index: 0 })
+        }
+    }
+
+    fn string(&self, index: usize) -> &[u8] {
+        let offset = payload(self.words[index]);
+        let length = self.words[index + 1] as usize;
+        let (buffer, start) = if offset & SIDE_BUFFER != 0 {
+            (&self.strings[..], (offset & !SIDE_BUFFER) as usize)
+        } else {
+            (self.input, offset as usize)
+        };
+        &buffer[start..start + length]
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+/// A position on a tape, at the start of a value.
+#[derive(Debug, Copy, Clone)]
+pub struct Cursor<'t> {
+    tape: &'t Tape<'t>,
+    index: usize,
+}
+
+impl<'t> Cursor<'t> {
+    /// Index of the value on the tape.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    fn word(&self) -> u64 {
+        self.tape.words[self.index]
+    }
+
+    pub fn kind(&self) -> Kind {
+        match tag(self.word()) {
+            b'{' => Kind::Object,
+            b'[' => Kind::Array,
+            b'"' => Kind::String,
+            b'l' | b'u' | b'd' => Kind::Number,
+            b't' | b'f' => Kind::Bool,
+            _ => Kind::Null,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.kind() == Kind::Null
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match tag(self.word()) {
+            b't' => Some(true),
+            b'f' => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<Number> {
+        let bits = self.tape.words.get(self.index + 1).cloned();
+        match (tag(self.word()), bits) {
+            (b'l', Some(bits)) => Some(Number::Integer(bits as i64)),
+            (b'u', Some(bits)) => Some(Number::Unsigned(bits)),
+            (b'd', Some(bits)) => Some(Number::Float(f64::from_bits(bits))),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'t str> {
+        match self.kind() {
+            Kind::String => str::from_utf8(self.tape.string(self.index)).ok(),
+            _ => None,
+        }
+    }
+
+    /// Index just past this value, found without looking at its children.
+    pub fn end(&self) -> usize {
+        match tag(self.word()) {
+            b'{' | b'[' => payload(self.word()) as usize,
+            b'"' | b'l' | b'u' | b'd' => self.index + 2,
+            _ => self.index + 1,
+        }
+    }
+
+    /// The elements if this is an array, the values if this is an object.
+    /// Empty otherwise.
+    pub fn children(&self) -> Children<'t> {
+        let (index, end) = match self.kind() {
+            Kind::Array | Kind::Object => (self.index + 1, self.end() - 1),
+            _ => (0, 0),
+        };
+        Children {
+            tape: self.tape,
+            index,
+            end,
+            object: self.kind() == Kind::Object,
+        }
+    }
+
+    /// The members if this is an object. Empty otherwise.
+    pub fn members(&self) -> Members<'t> {
+        let children = match self.kind() {
+            Kind::Object => self.children(),
+            _ => Children { tape: self.tape, index: 0, end: 0, object: true },
+        };
+        Members { children }
+    }
+
+    /// The value of the first member named `key`, if this is an object.
+    pub fn get(&self, key: &str) -> Option<Cursor<'t>> {
+        self.members()
+            .find(|&(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    /// The element at `index`, if this is an array.
+    pub fn get_at(&self, index: usize) -> Option<Cursor<'t>> {
+        match self.kind() {
+            Kind::Array => self.children().nth(index),
+            _ => None,
+        }
+    }
+}
+
+/// Iterator over the children of an array or object.
+#[derive(Debug, Clone)]
+pub struct Children<'t> {
+    tape: &'t Tape<'t>,
+    index: usize,
+    end: usize,
+    // Skip the key in front of every value.
+    object: bool,
+}
+
+impl<'t> Iterator for Children<'t> {
+    type Item = Cursor<'t>;
+
+    fn next(&mut self) -> Option<Cursor<'t>> {
+        if self.index >= self.end {
+            return None;
+        }
+        if self.object {
+            self.index += 2;
+        }
+        let cursor = Cursor { tape: self.tape, index: self.index };
+        self.index = cursor.end();
+        Some(cursor)
+    }
+}
+
+/// Iterator over the keys and values of an object.
+#[derive(Debug, Clone)]
+pub struct Members<'t> {
+    children: Children<'t>,
+}
+
+impl<'t> Iterator for Members<'t> {
+    type Item = (&'t str, Cursor<'t>);
+
+    fn next(&mut self) -> Option<(&'t str, Cursor<'t>)> {
+        let key_index = self.children.index;
+        let value = self.children.next()?;
+        let key = Cursor { tape: self.children.tape, index: key_index };
+        Some((key.as_str().unwrap_or(""), value))
+    }
+}
+
+#[derive(Debug)]
+enum PendingString {
+    // Nothing appended yet.
+    Empty,
+    // A single range of the input.
+    Input(Range),
+    // Bytes in the side buffer, from the given offset on.
+    Side(usize),
+}
+
+/// Sink writing a `Tape`.
+///
+/// Strings without escapes point into `input` when they can be found there.
+/// Everything else is copied into the side buffer, so `new_windowed` works
+/// with any source.
+#[derive(Debug)]
+pub struct TapeSink<'a> {
+    tape: Tape<'a>,
+    // Indices of the starts of the containers being read.
+    open: Vec<usize>,
+    string: PendingString,
+    // Set when a part of the current string was not in the window.
+    unavailable: bool,
+}
+
+impl<'a> TapeSink<'a> {
+    pub fn new(input: &'a [u8]) -> TapeSink<'a> {
+        TapeSink {
+            tape: Tape {
+                words: Vec::new(),
+                strings: Vec::new(),
+                input,
+            },
+            open: Vec::new(),
+            string: PendingString::Empty,
+            unavailable: false,
+        }
+    }
+
+    /// Creates a sink that copies every string into the side buffer.
+    pub fn new_windowed() -> TapeSink<'static> {
+        TapeSink::new(&[])
+    }
+
+    /// The tape written, if a complete document has been read.
+    pub fn into_tape(self) -> Option<Tape<'a>> {
+        if self.open.is_empty() && !self.tape.words.is_empty() {
+            Some(self.tape)
+        } else {
+            None
+        }
+    }
+
+    fn open(&mut self, bracket: u8) {
+        self.open.push(self.tape.words.len());
+        self.tape.words.push(word(bracket, 0));
+    }
+
+    // Writes the end of a container, and links its start to it.
+    fn close(&mut self, bracket: u8) {
+        if let Some(start) = self.open.pop() {
+            self.tape.words.push(word(bracket, start as u64));
+            let end = self.tape.words.len() as u64;
+            self.tape.words[start] = word(tag(self.tape.words[start]), end);
+        }
+    }
+
+    // Moves the current string into the side buffer, if it is not there yet.
+    fn side_buffer(&mut self) -> &mut Vec<u8> {
+        let strings = &mut self.tape.strings;
+        match mem::replace(&mut self.string, PendingString::Empty) {
+            PendingString::Empty => self.string = PendingString::Side(strings.len()),
+            PendingString::Input(range) => {
+                let offset = strings.len();
+                strings.extend_from_slice(&self.tape.input[range.start..range.end]);
+                self.string = PendingString::Side(offset);
+            }
+            side => self.string = side,
+        }
+        strings
+    }
+}
+
+impl<'a> Bailable for TapeSink<'a> {
+    type Bail = ValueError;
+}
+
+impl<'a> Sink for TapeSink<'a> {
+    fn push_map(&mut self, _pos: Position) {
+        self.open(b'{');
+    }
+    fn push_array(&mut self, _pos: Position) {
+        self.open(b'[');
+    }
+
+    fn push_number(&mut self, pos: Position, number: NumberData) -> Result<(), Self::Bail> {
+        let input = self.tape.input;
+        self.push_number_window(pos, number, Window::from(input))
+    }
+    fn push_number_window(&mut self, _pos: Position, number: NumberData, window: Window) -> Result<(), Self::Bail> {
+        let (tag, bits) = match Number::from_data(&number, window).map_err(ValueError::Number)? {
+            Number::Integer(value) => (b'l', value as u64),
+            Number::Unsigned(value) => (b'u', value),
+            Number::Float(value) => (b'd', value.to_bits()),
+        };
+        self.tape.words.push(word(tag, 0));
+        self.tape.words.push(bits);
+        Ok(())
+    }
+    fn push_bool(&mut self, _pos: Position, boolean: bool) -> Result<(), Self::Bail> {
+        self.tape.words.push(word(if boolean { b't' } else { b'f' }, 0));
+        Ok(())
+    }
+    fn push_null(&mut self, _pos: Position) -> Result<(), Self::Bail> {
+        self.tape.words.push(word(b'n', 0));
+        Ok(())
+    }
+
+    fn start_string(&mut self, _pos: StringPosition) {
+        self.string = PendingString::Empty;
+        self.unavailable = false;
+    }
+    fn append_string_range(&mut self, string: Range) {
+        let input = self.tape.input;
+        self.append_string_window(string, Window::from(input));
+    }
+    fn append_string_window(&mut self, string: Range, window: Window) {
+        let in_input = string.end <= self.tape.input.len();
+        match self.string {
+            PendingString::Empty if in_input => self.string = PendingString::Input(string),
+            _ => match window.get(string) {
+                Some(bytes) => self.side_buffer().extend_from_slice(bytes),
+                None => self.unavailable = true,
+            },
+        }
+    }
+    fn append_string_single(&mut self, character: u8) {
+        self.side_buffer().push(character);
+    }
+    fn append_string_codepoint(&mut self, codepoint: char) {
+        let mut buf = [0; 4];
+        self.side_buffer().extend_from_slice(codepoint.encode_utf8(&mut buf).as_bytes());
+    }
+    fn finalize_string(&mut self, _pos: StringPosition) -> Result<(), Self::Bail> {
+        if mem::replace(&mut self.unavailable, false) {
+            return Err(ValueError::Unavailable);
+        }
+        let (offset, length) = match mem::replace(&mut self.string, PendingString::Empty) {
+            PendingString::Empty => (0, 0),
+            PendingString::Input(range) => (range.start as u64, range.size()),
+            PendingString::Side(offset) => (offset as u64 | SIDE_BUFFER, self.tape.strings.len() - offset),
+        };
+        self.tape.words.push(word(b'"', offset));
+        self.tape.words.push(length as u64);
+        Ok(())
+    }
+
+    fn finalize_array(&mut self, _pos: Position) -> Result<(), Self::Bail> {
+        self.close(b']');
+        Ok(())
+    }
+    fn finalize_map(&mut self, _pos: Position) -> Result<(), Self::Bail> {
+        self.close(b'}');
+        Ok(())
+    }
+
+    fn pop_into_map(&mut self) {}
+    fn pop_into_array(&mut self) {}
+}
@@ -0,0 +1,451 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::error;
+use std::fmt;
+use std::mem;
+use std::ops::Index;
+use std::slice;
+
+use ::Bailable;
+use super::{Sink, NumberData, Position, StringPosition};
+use super::build::Builder;
+use super::path::PathSink;
+use ::input::{Range, Window};
+use ::number::NumberError;
+
+/// A JSON value. By default it owns all of its data, `S` is the type of
+/// its strings and keys.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<S = String> {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(S),
+    Array(Vec<Value<S>>),
+    Object(Map<S>),
+}
+
+impl<S> Value<S> where S: Borrow<str> {
+    pub fn is_null(&self) -> bool {
+        matches!(*self, Value::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<Number> {
+        match *self {
+            Value::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(|number| number.as_i64())
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(|number| number.as_u64())
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(|number| number.as_f64())
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref string) => Some(string.borrow()),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value<S>>> {
+        match *self {
+            Value::Array(ref array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Map<S>> {
+        match *self {
+            Value::Object(ref map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// The value of `key` if this is an object containing it.
+    pub fn get(&self, key: &str) -> Option<&Value<S>> {
+        self.as_object().and_then(|map| map.get(key))
+    }
+
+    /// The element at `index` if this is an array long enough.
+    pub fn get_at(&self, index: usize) -> Option<&Value<S>> {
+        self.as_array().and_then(|array| array.get(index))
+    }
+}
+
+/// Looks up a key of an object. Gives `Value::Null` if this is not an
+/// object, or the key is missing.
+impl<'a, S> Index<&'a str> for Value<S> where S: Borrow<str> {
+    type Output = Value<S>;
+
+    fn index(&self, key: &'a str) -> &Value<S> {
+        self.get(key).unwrap_or(&Value::Null)
+    }
+}
+
+/// Looks up an element of an array. Gives `Value::Null` if this is not an
+/// array, or the index is out of bounds.
+impl<S> Index<usize> for Value<S> where S: Borrow<str> {
+    type Output = Value<S>;
+
+    fn index(&self, index: usize) -> &Value<S> {
+        self.get_at(index).unwrap_or(&Value::Null)
+    }
+}
+
+/// A number, typed by how it is written. Integer literals are stored as
+/// integers if they fit in an `i64` or `u64`, everything else as the nearest
+/// `f64`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Number {
+    Integer(i64),
+    /// Only used for integers above `i64::MAX`.
+    Unsigned(u64),
+    Float(f64),
+}
+
+impl Number {
+    pub fn from_data(number: &NumberData, window: Window<'_>) -> Result<Number, NumberError> {
+        if number.is_integer_literal() {
+            if let Ok(value) = number.to_i64(window) {
+                return Ok(Number::Integer(value));
+            }
+            if let Ok(value) = number.to_u64(window) {
+                return Ok(Number::Unsigned(value));
+            }
+        }
+        number.to_f64(window).map(Number::Float)
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Number::Integer(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Number::Integer(value) if value >= 0 => Some(value as u64),
+            Number::Unsigned(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The number as an `f64`, rounding integers that do not fit exactly.
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Number::Integer(value) => value as f64,
+            Number::Unsigned(value) => value as f64,
+            Number::Float(value) => value,
+        }
+    }
+}
+
+// Maps with up to this many members are searched without the index.
+const LINEAR: usize = 8;
+// Free slot in the index.
+const EMPTY: usize = usize::MAX;
+
+/// Object members in the order they were read, with lookup by key.
+#[derive(Clone)]
+pub struct Map<K = String, V = Value<K>> {
+    entries: Vec<(K, V)>,
+    // Open addressing table of positions in `entries`, placed by the hash of
+    // their key, so the keys are not stored twice. Empty for small maps.
+    index: Vec<usize>,
+    hasher: RandomState,
+}
+
+impl<K, V> Default for Map<K, V> {
+    fn default() -> Self {
+        Map {
+            entries: Vec::new(),
+            index: Vec::new(),
+            hasher: RandomState::new(),
+        }
+    }
+}
+
+impl<K, V> Map<K, V> where K: Borrow<str> {
+    pub fn new() -> Self {
+        Map::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.find(key).is_some()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.find(key).map(|i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        match self.find(key) {
+            Some(i) => Some(&mut self.entries[i].1),
+            None => None,
+        }
+    }
+
+    /// Adds a member at the end. If the key is present already, its value is
+    /// replaced in place instead, and the old value returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(i) = self.find(key.borrow()) {
+            return Some(mem::replace(&mut self.entries[i].1, value));
+        }
+        self.entries.push((key, value));
+        let len = self.entries.len();
+        if len > LINEAR {
+            // Keeps the index at most half full.
+            if self.index.len() < len * 2 {
+                self.index = vec![EMPTY; (len * 4).next_power_of_two()];
+                for i in 0..len {
+                    self.place(i);
+                }
+            } else {
+                self.place(len - 1);
+            }
+        }
+        None
+    }
+
+    // The position of the member with `key`.
+    fn find(&self, key: &str) -> Option<usize> {
+        if self.index.is_empty() {
+            return self.entries.iter().position(|entry| entry.0.borrow() == key);
+        }
+        let mask = self.index.len() - 1;
+        let mut slot = self.hasher.hash_one(key) as usize & mask;
+        loop {
+            match self.index[slot] {
+                EMPTY => return None,
+                i if self.entries[i].0.borrow() == key => return Some(i),
+                _ => slot = (slot + 1) & mask,
+            }
+        }
+    }
+
+    // Adds the member at position `i` to the index.
+    fn place(&mut self, i: usize) {
+        let mask = self.index.len() - 1;
+        let mut slot = self.hasher.hash_one(self.entries[i].0.borrow()) as usize & mask;
+        while self.index[slot] != EMPTY {
+            slot = (slot + 1) & mask;
+        }
+        self.index[slot] = i;
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, (K, V)> {
+        self.entries.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.0.borrow())
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|entry| &entry.1)
+    }
+}
+
+/// Formats the members only, in order.
+impl<K, V> fmt::Debug for Map<K, V> where K: fmt::Debug, V: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.entries.iter().map(|entry| (&entry.0, &entry.1))).finish()
+    }
+}
+
+/// Maps are equal if they have the same members in the same order.
+impl<K, V> PartialEq for Map<K, V> where K: PartialEq, V: PartialEq {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a Map<K, V> where K: Borrow<str> {
+    type Item = &'a (K, V);
+    type IntoIter = slice::Iter<'a, (K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Gives `Value::Null` if the key is missing.
+impl<'a, K> Index<&'a str> for Map<K> where K: Borrow<str> {
+    type Output = Value<K>;
+
+    fn index(&self, key: &'a str) -> &Value<K> {
+        self.get(key).unwrap_or(&Value::Null)
+    }
+}
+
+/// What to do when an object contains the same key more than once.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// Abort with `ValueError::DuplicateKey`.
+    Reject,
+    /// Keep the first value.
+    First,
+    /// Keep the last value, at the position of the first.
+    #[default]
+    Last,
+}
+
+/// Bail of `ValueSink`. Aborts parsing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueError {
+    /// A key was repeated while using `DuplicateKeys::Reject`.
+    DuplicateKey(String),
+    /// A number could not be converted.
+    Number(NumberError),
+    /// A string range was not in the window.
+    Unavailable,
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValueError::DuplicateKey(ref key) => write!(f, "duplicate key {:?}", key),
+            ValueError::Number(ref err) => err.fmt(f),
+            ValueError::Unavailable => f.write_str("string is not in the window"),
+        }
+    }
+}
+
+impl error::Error for ValueError {}
+
+/// Sink building a `Value`.
+///
+/// Ranges are resolved against the window of the source, so this is meant to
+/// be used through `SourceSink`, or `Decoder::decode_with`.
+#[derive(Debug, Default)]
+pub struct ValueSink {
+    builder: Builder<String>,
+    string: Vec<u8>,
+    // Set when a part of the current string was not in the window.
+    unavailable: bool,
+}
+
+impl ValueSink {
+    pub fn new() -> ValueSink {
+        ValueSink::default()
+    }
+
+    pub fn with_duplicate_keys(duplicate_keys: DuplicateKeys) -> ValueSink {
+        ValueSink {
+            builder: Builder::new(duplicate_keys),
+            ..ValueSink::default()
+        }
+    }
+
+    /// The value read, if a complete document has been read.
+    pub fn into_value(self) -> Option<Value> {
+        self.builder.into_value()
+    }
+
+    fn take_string(&mut self) -> Result<String, ValueError> {
+        let bytes = mem::take(&mut self.string);
+        if mem::replace(&mut self.unavailable, false) {
+            return Err(ValueError::Unavailable);
+        }
+        // The tokenizer only passes on valid UTF-8.
+        Ok(String::from_utf8(bytes)
+           .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned()))
+    }
+}
+
+impl Bailable for ValueSink {
+    type Bail = ValueError;
+}
+
+impl PathSink for ValueSink {}
+
+impl Sink for ValueSink {
+    fn push_map(&mut self, _pos: Position) {
+        self.builder.push(Value::Object(Map::new()));
+    }
+    fn push_array(&mut self, _pos: Position) {
+        self.builder.push(Value::Array(Vec::new()));
+    }
+
+    fn push_number(&mut self, pos: Position, number: NumberData) -> Result<(), Self::Bail> {
+        self.push_number_window(pos, number, Window::from(&[][..]))
+    }
+    fn push_number_window(&mut self, _pos: Position, number: NumberData, window: Window) -> Result<(), Self::Bail> {
+        let number = Number::from_data(&number, window).map_err(ValueError::Number)?;
+        self.builder.push(Value::Number(number));
+        Ok(())
+    }
+    fn push_bool(&mut self, _pos: Position, boolean: bool) -> Result<(), Self::Bail> {
+        self.builder.push(Value::Bool(boolean));
+        Ok(())
+    }
+    fn push_null(&mut self, _pos: Position) -> Result<(), Self::Bail> {
+        self.builder.push(Value::Null);
+        Ok(())
+    }
+
+    fn start_string(&mut self, _pos: StringPosition) {
+        self.string.clear();
+        self.unavailable = false;
+    }
+    fn append_string_range(&mut self, string: Range) {
+        self.append_string_window(string, Window::from(&[][..]));
+    }
+    fn append_string_window(&mut self, string: Range, window: Window) {
+        match window.get(string) {
+            Some(bytes) => self.string.extend_from_slice(bytes),
+            None => self.unavailable = true,
+        }
+    }
+    fn append_string_single(&mut self, character: u8) {
+        self.string.push(character);
+    }
+    fn append_string_codepoint(&mut self, codepoint: char) {
+        let mut buf = [0; 4];
+        self.string.extend_from_slice(codepoint.encode_utf8(&mut buf).as_bytes());
+    }
+    fn finalize_string(&mut self, pos: StringPosition) -> Result<(), Self::Bail> {
+        let string = self.take_string()?;
+        self.builder.push_string(pos, string)
+    }
+
+    fn finalize_array(&mut self, _pos: Position) -> Result<(), Self::Bail> {
+        Ok(())
+    }
+    fn finalize_map(&mut self, _pos: Position) -> Result<(), Self::Bail> {
+        Ok(())
+    }
+
+    fn pop_into_map(&mut self) {
+        self.builder.pop_into_map();
+    }
+    fn pop_into_array(&mut self) {
+        self.builder.pop_into_array();
+    }
+}
@@ -0,0 +1,88 @@
+use super::{Source, PeekResult};
+use ::Bailable;
+use ::input::{Pos, Window};
+
+/// Source that is fed chunks of input as they arrive.
+///
+/// Running out of input bails instead of signaling EOF, until `finish` is
+/// called to mark that no more chunks will follow.
+///
+/// All chunks are kept, so positions handed to the sink index directly into
+/// `buffer()`.
+#[derive(Debug)]
+pub struct ChunkSource {
+    buf: Vec<u8>,
+    pos: usize,
+    finished: bool,
+}
+
+impl ChunkSource {
+
+    pub fn new() -> ChunkSource {
+        ChunkSource {
+            buf: Vec::new(),
+            pos: 0,
+            finished: false,
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        assert!(!self.finished, "pushed chunk after finish");
+        self.buf.extend_from_slice(chunk);
+    }
+
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// All chunks pushed so far.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+}
+
+impl Default for ChunkSource {
+    fn default() -> ChunkSource {
+        ChunkSource::new()
+    }
+}
+
+impl Bailable for ChunkSource {
+    type Bail = ();
+}
+
+impl Source for ChunkSource {
+
+    fn position(&self) -> Pos {
+        self.pos.into()
+    }
+
+    fn skip(&mut self, num: usize) {
+        self.pos += num;
+    }
+
+    fn peek_char(&mut self) -> PeekResult<Self::Bail> {
+        if self.pos < self.buf.len() {
+            PeekResult::Ok(self.buf[self.pos])
+        } else if self.finished {
+            PeekResult::Eof
+        } else {
+            PeekResult::Bail(())
+        }
+    }
+
+    fn peek_slice(&self, length: usize) -> Option<&[u8]> {
+        let pos = self.pos;
+        self.buf.get(pos..(pos+length))
+    }
+
+    fn window(&self) -> Window<'_> {
+        Window::from(&self.buf[..])
+    }
+
+}
@@ -1,8 +1,30 @@
+use std::io::{self, Read};
+
 use ::PResult;
 use ::Bailable;
-use ::input::Pos;
+use ::input::{Pos, Window};
 
 pub mod string;
+pub mod reader;
+pub mod chunked;
+pub mod stream;
+
+// Appends up to `chunk_size` bytes from `reader` to `buf`, retrying reads
+// that were interrupted. Returns the number of bytes read, 0 at EOF.
+fn read_chunk<R>(reader: &mut R, buf: &mut Vec<u8>, chunk_size: usize) -> io::Result<usize> where R: Read {
+    let len = buf.len();
+    buf.resize(len + chunk_size, 0);
+
+    let result = loop {
+        match reader.read(&mut buf[len..]) {
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            result => break result,
+        }
+    };
+
+    buf.truncate(len + *result.as_ref().unwrap_or(&0));
+    result
+}
 
 pub enum PeekResult<Bail> {
     Ok(u8),
@@ -15,4 +37,15 @@ pub trait Source: Bailable {
     fn skip(&mut self, num: usize);
     fn peek_char(&mut self) -> PeekResult<Self::Bail>;
     fn peek_slice<'a>(&'a self, length: usize) -> Option<&'a [u8]>;
+    /// The bytes the source still holds, which ranges passed to the sink
+    /// are resolved against. The parser itself never reads it.
+    ///
+    /// The default holds none, so sources that do not override it only work
+    /// with sinks holding on to the input themselves, like
+    /// `BorrowedValueSink`. `ValueSink` and `TapeSink` fail with
+    /// `ValueError::Unavailable` without one, and `EnumSink` and
+    /// `pull::Events` panic.
+    fn window(&self) -> Window<'_> {
+        Window::new(self.position(), &[])
+    }
 }
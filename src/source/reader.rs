@@ -0,0 +1,112 @@
+use std::io::{self, Read};
+
+use super::{Source, PeekResult, read_chunk};
+use ::Bailable;
+use ::input::{Pos, Window};
+
+const DEFAULT_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Source reading from anything implementing `std::io::Read`.
+///
+/// The source never blocks inside the parser. When everything read so far
+/// has been consumed it bails, and the caller is expected to call `fill`
+/// before running the tokenizer again:
+///
+/// ```ignore
+/// loop {
+///     match tokenizer.run(&mut ss) {
+///         Err(ParseError::SourceBail(BailVariant::Source(()))) => {
+///             ss.source.fill()?;
+///         },
+///         ...
+///     }
+/// }
+/// ```
+///
+/// All data read is kept, so positions handed to the sink index directly
+/// into `buffer()`.
+#[derive(Debug)]
+pub struct ReaderSource<R> {
+    reader: R,
+    buf: Vec<u8>,
+    pos: usize,
+    chunk_size: usize,
+    eof: bool,
+}
+
+impl<R> ReaderSource<R> where R: Read {
+
+    pub fn new(reader: R) -> ReaderSource<R> {
+        ReaderSource::with_chunk_size(reader, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(reader: R, chunk_size: usize) -> ReaderSource<R> {
+        assert!(chunk_size > 0);
+        ReaderSource {
+            reader,
+            buf: Vec::new(),
+            pos: 0,
+            chunk_size,
+            eof: false,
+        }
+    }
+
+    /// Reads the next chunk from the reader into the buffer.
+    /// Returns the number of bytes read, 0 means the reader hit EOF.
+    pub fn fill(&mut self) -> io::Result<usize> {
+        let result = read_chunk(&mut self.reader, &mut self.buf, self.chunk_size);
+        if let Ok(0) = result {
+            self.eof = true;
+        }
+        result
+    }
+
+    /// All data read from the reader so far.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.eof
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+}
+
+impl<R> Bailable for ReaderSource<R> where R: Read {
+    type Bail = ();
+}
+
+impl<R> Source for ReaderSource<R> where R: Read {
+
+    fn position(&self) -> Pos {
+        self.pos.into()
+    }
+
+    fn skip(&mut self, num: usize) {
+        self.pos += num;
+    }
+
+    fn peek_char(&mut self) -> PeekResult<Self::Bail> {
+        if self.pos < self.buf.len() {
+            PeekResult::Ok(self.buf[self.pos])
+        } else if self.eof {
+            PeekResult::Eof
+        } else {
+            PeekResult::Bail(())
+        }
+    }
+
+    fn peek_slice(&self, length: usize) -> Option<&[u8]> {
+        let pos = self.pos;
+        self.buf.get(pos..(pos+length))
+    }
+
+    fn window(&self) -> Window<'_> {
+        Window::from(&self.buf[..])
+    }
+
+}
@@ -0,0 +1,122 @@
+use std::io::{self, Read};
+
+use super::{Source, PeekResult, read_chunk};
+use ::Bailable;
+use ::input::{Pos, Window};
+
+const DEFAULT_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Source reading from anything implementing `std::io::Read`, only keeping
+/// the part of the input that is still needed in memory.
+///
+/// Bails exactly like `ReaderSource` when the buffered input is consumed.
+/// Before calling `fill`, the caller should call `discard_before` with the
+/// earliest position the tokenizer still needs, so that the buffer only
+/// ever holds the token currently being read plus one chunk:
+///
+/// ```ignore
+/// Err(ParseError::SourceBail(BailVariant::Source(()))) => {
+///     let keep = tokenizer.retain_from().unwrap_or(ss.source.position());
+///     ss.source.discard_before(keep);
+///     ss.source.fill()?;
+/// },
+/// ```
+///
+/// Positions are still absolute offsets into the stream. Sinks must resolve
+/// the ranges they are given against the window of the source while
+/// handling the event (see `Sink::append_string_window`), as the bytes may
+/// be gone afterwards.
+#[derive(Debug)]
+pub struct StreamSource<R> {
+    reader: R,
+    buf: Vec<u8>,
+    // Absolute position of the first byte in `buf`.
+    offset: usize,
+    pos: usize,
+    chunk_size: usize,
+    eof: bool,
+}
+
+impl<R> StreamSource<R> where R: Read {
+
+    pub fn new(reader: R) -> StreamSource<R> {
+        StreamSource::with_chunk_size(reader, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(reader: R, chunk_size: usize) -> StreamSource<R> {
+        assert!(chunk_size > 0);
+        StreamSource {
+            reader,
+            buf: Vec::new(),
+            offset: 0,
+            pos: 0,
+            chunk_size,
+            eof: false,
+        }
+    }
+
+    /// Reads the next chunk from the reader into the buffer.
+    /// Returns the number of bytes read, 0 means the reader hit EOF.
+    pub fn fill(&mut self) -> io::Result<usize> {
+        let result = read_chunk(&mut self.reader, &mut self.buf, self.chunk_size);
+        if let Ok(0) = result {
+            self.eof = true;
+        }
+        result
+    }
+
+    /// Drops all buffered input before `pos`. Ranges before `pos` can no
+    /// longer be resolved after this.
+    pub fn discard_before(&mut self, pos: Pos) {
+        let end = self.offset + self.buf.len();
+        let keep = ::std::cmp::min(pos.0, end);
+        if keep > self.offset {
+            self.buf.drain(..(keep - self.offset));
+            self.offset = keep;
+        }
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.eof
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+}
+
+impl<R> Bailable for StreamSource<R> where R: Read {
+    type Bail = ();
+}
+
+impl<R> Source for StreamSource<R> where R: Read {
+
+    fn position(&self) -> Pos {
+        self.pos.into()
+    }
+
+    fn skip(&mut self, num: usize) {
+        self.pos += num;
+    }
+
+    fn peek_char(&mut self) -> PeekResult<Self::Bail> {
+        if self.pos < self.offset + self.buf.len() {
+            PeekResult::Ok(self.buf[self.pos - self.offset])
+        } else if self.eof {
+            PeekResult::Eof
+        } else {
+            PeekResult::Bail(())
+        }
+    }
+
+    fn peek_slice(&self, length: usize) -> Option<&[u8]> {
+        let pos = self.pos - self.offset;
+        self.buf.get(pos..(pos+length))
+    }
+
+    fn window(&self) -> Window<'_> {
+        Window::new(self.offset.into(), &self.buf)
+    }
+
+}
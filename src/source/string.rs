@@ -1,6 +1,6 @@
 use super::{Source, PeekResult};
 use ::Bailable;
-use ::input::Pos;
+use ::input::{Pos, Window};
 
 #[derive(Debug)]
 pub struct VecSource {
@@ -47,6 +47,64 @@ impl Source for VecSource {
         self.vec.get(pos..(pos+length))
     }
 
+    fn window(&self) -> Window<'_> {
+        Window::from(&self.vec[..])
+    }
+
+}
+
+/// Same as `VecSource`, but borrows the input instead of owning it.
+///
+/// This lets a sink hold on to the same buffer to resolve the `Range`s it is
+/// handed, without copying the document.
+#[derive(Debug)]
+pub struct SliceSource<'a> {
+    slice: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SliceSource<'a> {
+
+    pub fn new(slice: &'a [u8]) -> SliceSource<'a> {
+        SliceSource {
+            slice,
+            pos: 0,
+        }
+    }
+
+}
+
+impl<'a> Bailable for SliceSource<'a> {
+    type Bail = ();
+}
+
+impl<'a> Source for SliceSource<'a> {
+
+    fn position(&self) -> Pos {
+        self.pos.into()
+    }
+
+    fn skip(&mut self, num: usize) {
+        self.pos += num;
+    }
+
+    fn peek_char(&mut self) -> PeekResult<Self::Bail> {
+        if self.pos >= self.slice.len() {
+            PeekResult::Eof
+        } else {
+            PeekResult::Ok(self.slice[self.pos])
+        }
+    }
+
+    fn peek_slice(&self, length: usize) -> Option<&[u8]> {
+        let pos = self.pos;
+        self.slice.get(pos..(pos+length))
+    }
+
+    fn window(&self) -> Window<'_> {
+        Window::from(self.slice)
+    }
+
 }
 
 #[derive(Debug)]
@@ -102,4 +160,8 @@ impl Source for VecSourceB {
         None
     }
 
+    fn window(&self) -> Window<'_> {
+        Window::from(&self.vec[..])
+    }
+
 }
@@ -1,10 +1,11 @@
 use ::PResult;
 use ::Bailable;
-use ::error::{ParseError, Unexpected};
+use ::error::{ParseError, Unexpected, Expected, Expectation};
 use ::input::{Pos, Range};
 use ::source::{Source, PeekResult};
-use ::sink::Sink;
-use ::parser::ParserState;
+use ::sink::{Sink, StringKind};
+use ::parser::{ParserState, NumberKind};
+use ::options::{Options, Limits, Documents, Dialect};
 use ::utf8;
 
 #[derive(Debug, Copy, Clone)]
@@ -13,6 +14,10 @@ enum StringState {
     StartEscape,
     UnicodeEscape(u8, u32, Option<u32>),
     StartUnicodeContinuation(StartContinuationState, u32),
+    // After `\0`, which JSON5 does not allow to be followed by a digit.
+    ZeroEscape,
+    // After a backslash and CR, which may be followed by LF.
+    LineContinuation,
     End,
 }
 
@@ -22,20 +27,120 @@ enum StartContinuationState {
     Uchar,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Literal {
+    True,
+    False,
+    Null,
+    Infinity,
+    NaN,
+}
+
+impl Literal {
+    fn bytes(self) -> &'static [u8] {
+        match self {
+            Literal::True => b"true",
+            Literal::False => b"false",
+            Literal::Null => b"null",
+            Literal::Infinity => b"Infinity",
+            Literal::NaN => b"NaN",
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 enum TokenState {
     None,
     String,
-    Number(Pos),
+    // Digits starting at the given position, and whether the first one is
+    // `0`.
+    Number(Pos, bool),
+    // Digits of a hexadecimal number starting at the given position.
+    Hex(Pos),
+    // An unquoted object key.
+    Identifier(utf8::DecodeState),
+    // Literal being matched, and the number of bytes matched so far.
+    Literal(Literal, u8),
+    // Scanning past a value the sink asked to skip.
+    Skip(SkipState),
+}
+
+#[derive(Debug, Copy, Clone)]
+struct SkipState {
+    // The quote that ends the string being skipped, if in one.
+    string: Option<u8>,
+    escape: bool,
+    comment: Option<Comment>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Comment {
+    // After the `/` that starts a comment.
+    Start,
+    Line,
+    Block,
+    // After a `*` in a block comment.
+    BlockStar,
+    // After the `*/` that ends a block comment.
+    End,
+}
+
+impl Comment {
+    // The state after `character`, or `None` if the `/` does not start a
+    // comment. Line comments end before the newline, which is not passed
+    // here.
+    fn next(self, character: u8) -> Option<Comment> {
+        Some(match (self, character) {
+            (Comment::Start, b'/') => Comment::Line,
+            (Comment::Start, b'*') => Comment::Block,
+            (Comment::Start, _) => return None,
+            (Comment::Line, _) => Comment::Line,
+            (Comment::Block, b'*') | (Comment::BlockStar, b'*') => Comment::BlockStar,
+            (Comment::BlockStar, b'/') => Comment::End,
+            (Comment::Block, _) | (Comment::BlockStar, _) => Comment::Block,
+            (Comment::End, _) => unreachable!(),
+        })
+    }
+}
+
+// Starts each document in `Documents::Sequence`.
+const RECORD_SEPARATOR: u8 = 0x1E;
+
+// Where the tokenizer is when the input holds several documents.
+#[derive(Debug, Copy, Clone)]
+enum DocumentState {
+    // Whether a record separator was read since the last document.
+    Between(bool),
+    Inside,
+    // After the root value, which ended at the given position.
+    After(Pos),
+    // Skipping the rest of a document that failed to parse.
+    Recovering(Pos, Unexpected),
 }
 
 #[derive(Debug)]
 pub struct TokenizerState {
     state: TokenState,
     parser: ParserState,
+    options: Options,
+    limits: Limits,
+
+    document: DocumentState,
+    document_start: Pos,
+    // Whether the root value is a number or literal, and so does not end
+    // with a delimiter.
+    document_scalar: bool,
 
     string_state: StringState,
     string_start: Pos,
+    string_kind: StringKind,
+
+    // Closing brackets of the containers open in a skipped value.
+    skip_stack: Vec<u8>,
+
+    // The comment being read, outside of skipped values.
+    comment: Option<Comment>,
+    comment_start: Pos,
 
     to_end: bool,
 }
@@ -48,12 +153,28 @@ macro_rules! unexpected {
 
 impl TokenizerState {
     pub fn new() -> TokenizerState {
+        TokenizerState::with_options(Options::default())
+    }
+
+    pub fn with_options(options: Options) -> TokenizerState {
         TokenizerState {
             state: TokenState::None,
-            parser: ParserState::new(),
+            parser: ParserState::with_options(options),
+            options,
+            limits: options.limits,
+
+            document: DocumentState::Between(false),
+            document_start: 0.into(),
+            document_scalar: false,
 
             string_state: StringState::None(utf8::UTF8_ACCEPT),
             string_start: 0.into(),
+            string_kind: StringKind::DoubleQuoted,
+
+            skip_stack: Vec::new(),
+
+            comment: None,
+            comment_start: 0.into(),
 
             to_end: true,
         }
@@ -65,23 +186,120 @@ impl TokenizerState {
         parser
     }
 
+    fn lines(&self) -> bool {
+        self.options.documents == Documents::Lines
+    }
+
+    fn json5(&self) -> bool {
+        self.options.dialect == Dialect::Json5
+    }
+
+    fn comments(&self) -> bool {
+        self.options.comments || self.json5()
+    }
+
+    fn non_finite(&self) -> bool {
+        self.options.non_finite || self.json5()
+    }
+
+    // The byte ending the current string. Unquoted keys have none.
+    fn string_quote(&self) -> Option<u8> {
+        match self.string_kind {
+            StringKind::DoubleQuoted => Some(b'"'),
+            StringKind::SingleQuoted => Some(b'\''),
+            StringKind::Identifier => None,
+        }
+    }
+
+    // The byte ending each document, if documents are framed, and the
+    // reason reported when it cuts one off.
+    fn record_end(&self) -> Option<(u8, Unexpected)> {
+        match self.options.documents {
+            Documents::Lines => Some((b'\n', Unexpected::LineEnd)),
+            Documents::Sequence => Some((RECORD_SEPARATOR, Unexpected::Truncated)),
+            Documents::Single | Documents::Concatenated => None,
+        }
+    }
+
+    fn ends_record(&self, character: u8) -> bool {
+        self.record_end().is_some_and(|(end, _)| character == end)
+    }
+
     fn skip_whitespace<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail>
         where SS: Source + Sink + Bailable
     {
-        while match ss.peek_char() {
-            PeekResult::Ok(b' ') => true,
-            PeekResult::Ok(b'\t') => true,
-            PeekResult::Ok(b'\n') => true,
-            PeekResult::Ok(b'\r') => true,
-            PeekResult::Ok(_) => false,
-            PeekResult::Bail(bail) => return Err(ParseError::SourceBail(bail)),
-            PeekResult::Eof => return Err(ParseError::Eof),
-        } {
+        loop {
+            if let Some(comment) = self.comment {
+                self.do_comment(ss, comment)?;
+            }
+            match ss.peek_char() {
+                PeekResult::Ok(b' ') | PeekResult::Ok(b'\t') | PeekResult::Ok(b'\r') => (),
+                PeekResult::Ok(b'\n') if !self.lines() => (),
+                PeekResult::Ok(b'/') if self.comments() => self.start_comment(ss),
+                PeekResult::Ok(_) => return Ok(()),
+                PeekResult::Bail(bail) => return Err(ParseError::SourceBail(bail)),
+                PeekResult::Eof => return Err(ParseError::Eof),
+            }
+            ss.skip(1);
+        }
+    }
+
+    // Called at the `/` that may start a comment, before it is skipped.
+    fn start_comment<SS>(&mut self, ss: &mut SS) where SS: Source {
+        self.comment_start = ss.position();
+        self.comment = Some(Comment::Start);
+    }
+
+    // Reads the rest of a comment, and passes it to the sink.
+    fn do_comment<SS>(&mut self, ss: &mut SS, mut comment: Comment) -> PResult<(), SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        while comment != Comment::End {
+            self.comment = Some(comment);
+            let character = match ss.peek_char() {
+                PeekResult::Ok(b'\n') if comment == Comment::Line => break,
+                PeekResult::Ok(character) => character,
+                PeekResult::Eof if comment == Comment::Line => break,
+                PeekResult::Eof => return unexpected!(ss, Unexpected::Eof),
+                PeekResult::Bail(bail) => return Err(ParseError::SourceBail(bail)),
+            };
+            if let Some((end, reason)) = self.record_end() {
+                if character == end {
+                    return unexpected!(ss, reason);
+                }
+            }
+            comment = match comment.next(character) {
+                Some(comment) => comment,
+                None => return Err(ParseError::Unexpected(self.comment_start, Unexpected::Character)),
+            };
             ss.skip(1);
         }
+        self.comment = None;
+        ss.comment(Range::new(self.comment_start, ss.position()));
         Ok(())
     }
 
+    // Finishes a comment between documents. Errors in it are returned to be
+    // recovered from like errors in a document, unless documents are not
+    // framed.
+    fn comment_between<SS>(&mut self, ss: &mut SS, comment: Comment) -> PResult<Option<(Pos, Unexpected)>, SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        match self.do_comment(ss, comment) {
+            Ok(()) => Ok(None),
+            Err(ParseError::Unexpected(pos, reason)) if self.options.documents != Documents::Concatenated => {
+                self.comment = None;
+                // The end of the input cuts off the record.
+                let reason = match (self.record_end(), reason) {
+                    (Some((_, cut_off)), Unexpected::Eof) => cut_off,
+                    _ => reason,
+                };
+                Ok(Some((pos, reason)))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
     fn read_char<SS>(&mut self, ss: &mut SS) -> PResult<u8, SS::Bail>
         where SS: Source + Sink + Bailable
     {
@@ -106,6 +324,17 @@ impl TokenizerState {
         let mut state = init_state;
 
         loop {
+            if let Some((end, reason)) = self.record_end() {
+                if curr_char == end {
+                    self.string_state = StringState::None(state);
+                    return unexpected!(ss, reason);
+                }
+            }
+            // Single quotes are not special to the decoder.
+            if curr_char == b'\'' && state == utf8::UTF8_ACCEPT && self.string_kind == StringKind::SingleQuoted {
+                self.string_state = StringState::None(state);
+                return Ok(state);
+            }
             state = utf8::decode(state, curr_char);
 
             match state {
@@ -154,8 +383,8 @@ impl TokenizerState {
                         // We reached the end of the string (unescaped quote).
                         // Return the last part of the string now, quote token
                         // next time.
-                        (b'"', utf8::UTF8_ACCEPT) |
-                        (b'"', utf8::UTF8_SPECIAL) => {
+                        (_, utf8::UTF8_ACCEPT) |
+                        (_, utf8::UTF8_SPECIAL) if Some(character) == self.string_quote() => {
                             let range = Range::new(self.string_start, ss.position());
                             self.string_start = ss.position();
                             self.string_state = StringState::End;
@@ -178,6 +407,11 @@ impl TokenizerState {
                                 self.parser.token_string_range(ss, range)?;
                             }
                         }
+                        // A double quote in a single quoted string.
+                        (b'"', utf8::UTF8_SPECIAL) => {
+                            self.string_state = StringState::None(utf8::UTF8_ACCEPT);
+                            ss.skip(1);
+                        }
                         // Normal characters.
                         // Use fast-path.
                         (_, utf8::UTF8_SPECIAL) => unreachable!(),
@@ -190,12 +424,18 @@ impl TokenizerState {
 
                 (StringState::End, _) => {
                     self.state = TokenState::None;
-                    return self.parser.token_quote(ss);
+                    return self.parser.token_string_delimiter(ss, self.string_kind);
                 }
 
                 // The last character was a backslash.
                 // We should expect an escaped character.
                 (StringState::StartEscape, PeekResult::Ok(character)) => {
+                    if let Some((end, reason)) = self.record_end() {
+                        if character == end {
+                            return unexpected!(ss, reason);
+                        }
+                    }
+                    let json5 = self.json5();
                     match character {
                         b'"' | b'\\' | b'/' => {
                             self.string_start = ss.position();
@@ -206,6 +446,33 @@ impl TokenizerState {
                             self.string_state = StringState::UnicodeEscape(4, 0, None);
                             ss.skip(1);
                         }
+                        b'x' if json5 => {
+                            self.string_state = StringState::UnicodeEscape(2, 0, None);
+                            ss.skip(1);
+                        }
+                        b'0' if json5 => {
+                            self.string_state = StringState::ZeroEscape;
+                            ss.skip(1);
+                        }
+                        // Line continuations, which add nothing to the string.
+                        b'\n' if json5 => {
+                            ss.skip(1);
+                            self.string_start = ss.position();
+                            self.string_state = StringState::None(utf8::UTF8_ACCEPT);
+                        }
+                        b'\r' if json5 => {
+                            self.string_state = StringState::LineContinuation;
+                            ss.skip(1);
+                        }
+                        // Any other character escapes itself in JSON5.
+                        _ if json5 && character >= 0x20 && !b"bfnrtv123456789".contains(&character) => {
+                            self.string_start = ss.position();
+                            self.string_state = StringState::None(utf8::UTF8_ACCEPT);
+                            // Multibyte characters are left to the decoder.
+                            if character < 0x80 {
+                                ss.skip(1);
+                            }
+                        }
                         _ => {
                             let escaped = match character {
                                 b'b' => 0x08,
@@ -213,6 +480,7 @@ impl TokenizerState {
                                 b'n' => b'\n',
                                 b'r' => b'\r',
                                 b't' => b'\t',
+                                b'v' if json5 => 0x0b,
                                 _ => return unexpected!(ss, Unexpected::InvalidEscape),
                             };
                             self.string_state = StringState::None(utf8::UTF8_ACCEPT);
@@ -279,6 +547,26 @@ impl TokenizerState {
                     self.string_state = StringState::UnicodeEscape(*count, *codepoint, lower);
                 }
 
+                (StringState::ZeroEscape, PeekResult::Ok(character)) => {
+                    if character.is_ascii_digit() {
+                        return unexpected!(ss, Unexpected::InvalidEscape);
+                    }
+                    self.string_state = StringState::None(utf8::UTF8_ACCEPT);
+                    self.string_start = ss.position();
+                    self.parser.token_string_single(ss, 0)?;
+                }
+
+                (StringState::LineContinuation, PeekResult::Ok(character)) => {
+                    if character == b'\n' {
+                        if self.lines() {
+                            return unexpected!(ss, Unexpected::LineEnd);
+                        }
+                        ss.skip(1);
+                    }
+                    self.string_state = StringState::None(utf8::UTF8_ACCEPT);
+                    self.string_start = ss.position();
+                }
+
                 (StringState::StartUnicodeContinuation(StartContinuationState::Slash, lower),
                  PeekResult::Ok(character)) => {
                     match character {
@@ -309,27 +597,250 @@ impl TokenizerState {
         }
     }
 
-    fn do_num<SS>(&mut self, ss: &mut SS, start: Pos) -> PResult<(), SS::Bail>
+    fn do_num<SS>(&mut self, ss: &mut SS, start: Pos, zero: bool) -> PResult<(), SS::Bail>
         where SS: Source + Sink + Bailable
     {
-        loop {
+        let next = loop {
             match ss.peek_char() {
 
                 // Walk through numbers
                 PeekResult::Ok(b'0'...b'9') => ss.skip(1),
 
                 // ... any other character breaks
-                PeekResult::Ok(_) => break,
+                PeekResult::Ok(character) => break Some(character),
 
                 // Errors
-                PeekResult::Eof => break,
+                PeekResult::Eof => break None,
                 PeekResult::Bail(bt) => return Err(ParseError::SourceBail(bt)),
             }
+        };
+
+        self.check_number_length(ss, start)?;
+        self.state = TokenState::None;
+        let range = Range::new(start, ss.position());
+        self.parser.token_number(ss, range, zero)?;
+
+        // A JSON5 hexadecimal number, the parser checks that the `0` starts
+        // it.
+        let only_zero = zero && range.size() == 1;
+        if self.json5() && only_zero && matches!(next, Some(b'x') | Some(b'X')) {
+            ss.skip(1);
+            self.state = TokenState::Hex(start);
+            return self.do_hex(ss, start);
         }
+        Ok(())
+    }
 
+    fn do_hex<SS>(&mut self, ss: &mut SS, start: Pos) -> PResult<(), SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        loop {
+            match ss.peek_char() {
+                PeekResult::Ok(character) if character.is_ascii_hexdigit() => ss.skip(1),
+                PeekResult::Ok(_) | PeekResult::Eof => break,
+                PeekResult::Bail(bt) => return Err(ParseError::SourceBail(bt)),
+            }
+        }
+
+        // The digits follow `0x`.
+        let digits = Range::new(Pos(start.0 + 2), ss.position());
+        if digits.size() == 0 {
+            return match ss.peek_char() {
+                PeekResult::Eof => unexpected!(ss, Unexpected::Eof),
+                _ => unexpected!(ss, Unexpected::Character),
+            };
+        }
+        self.check_number_length(ss, start)?;
         self.state = TokenState::None;
-        let pos = ss.position();
-        self.parser.token_number(ss, Range::new(start, pos))
+        self.parser.token_hex_number(ss, digits)
+    }
+
+    // Reads the rest of an unquoted object key, which started with an
+    // identifier start character.
+    fn do_identifier<SS>(&mut self, ss: &mut SS, mut state: utf8::DecodeState) -> PResult<(), SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        loop {
+            match ss.peek_char() {
+                PeekResult::Ok(character) if state == utf8::UTF8_ACCEPT && is_identifier_part(character) => ss.skip(1),
+                PeekResult::Ok(character) if state != utf8::UTF8_ACCEPT || character >= 0x80 => {
+                    state = utf8::decode(state, character);
+                    if state == utf8::UTF8_REJECT {
+                        return unexpected!(ss, Unexpected::InvalidUtf8);
+                    }
+                    ss.skip(1);
+                }
+                PeekResult::Ok(_) | PeekResult::Eof => break,
+                PeekResult::Bail(bt) => {
+                    self.state = TokenState::Identifier(state);
+                    return Err(ParseError::SourceBail(bt));
+                }
+            }
+        }
+        if state != utf8::UTF8_ACCEPT {
+            return unexpected!(ss, Unexpected::Eof);
+        }
+
+        // Closed like a quoted string, so that a sink bail resumes there.
+        let range = Range::new(self.string_start, ss.position());
+        self.state = TokenState::String;
+        self.string_state = StringState::End;
+        self.parser.token_string_range(ss, range)
+    }
+
+    fn start_string<SS>(&mut self, ss: &mut SS, kind: StringKind) -> PResult<(), SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        self.string_start = ss.position();
+        self.string_state = StringState::None(utf8::UTF8_ACCEPT);
+        self.string_kind = kind;
+        self.state = TokenState::String;
+        self.parser.token_string_delimiter(ss, kind)?;
+        if self.parser.skipping() {
+            self.start_skip(self.string_quote().unwrap());
+        }
+        Ok(())
+    }
+
+    // Matches the rest of a literal, byte by byte.
+    fn do_literal<SS>(&mut self, ss: &mut SS, literal: Literal, matched: u8) -> PResult<(), SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        let bytes = literal.bytes();
+        let mut matched = matched;
+
+        while (matched as usize) < bytes.len() {
+            match ss.peek_char() {
+                PeekResult::Ok(character) if character == bytes[matched as usize] => {
+                    ss.skip(1);
+                    matched += 1;
+                }
+                PeekResult::Ok(character) => {
+                    self.state = TokenState::Literal(literal, matched);
+                    return match self.record_end() {
+                        Some((end, reason)) if character == end => unexpected!(ss, reason),
+                        _ => unexpected!(ss, Unexpected::InvalidLiteral),
+                    };
+                }
+                PeekResult::Eof => {
+                    self.state = TokenState::Literal(literal, matched);
+                    return unexpected!(ss, Unexpected::Eof);
+                }
+                PeekResult::Bail(bt) => {
+                    self.state = TokenState::Literal(literal, matched);
+                    return Err(ParseError::SourceBail(bt));
+                }
+            }
+        }
+
+        self.state = TokenState::None;
+        let result = match literal {
+            Literal::True => self.parser.token_bool(ss, true),
+            Literal::False => self.parser.token_bool(ss, false),
+            Literal::Null => self.parser.token_null(ss),
+            Literal::Infinity | Literal::NaN => {
+                let kind = if literal == Literal::NaN { NumberKind::NaN } else { NumberKind::Infinity };
+                let start = Pos(ss.position().0 - bytes.len());
+                self.parser.token_special_number(ss, kind, start)
+            }
+        };
+
+        // Report parser errors at the start of the literal.
+        match result {
+            Err(ParseError::Unexpected(pos, reason)) =>
+                Err(ParseError::Unexpected(Pos(pos.0 - bytes.len()), reason)),
+            result => result,
+        }
+    }
+
+    // Starts skipping a value that ends with `close`, a closing bracket or
+    // quote.
+    fn start_skip(&mut self, close: u8) {
+        let string = close == b'"' || close == b'\'';
+        self.skip_stack.clear();
+        if !string {
+            self.skip_stack.push(close);
+        }
+        self.state = TokenState::Skip(SkipState {
+            string: if string { Some(close) } else { None },
+            escape: false,
+            comment: None,
+        });
+    }
+
+    // Scans to the end of a skipped value. Only brackets, quotes and escapes
+    // are looked at, nothing is passed to the sink.
+    fn do_skip<SS>(&mut self, ss: &mut SS, mut skip: SkipState) -> PResult<(), SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        loop {
+            let position = ss.position();
+            let character = match ss.peek_char() {
+                PeekResult::Ok(character) => character,
+                PeekResult::Eof => {
+                    self.state = TokenState::Skip(skip);
+                    return unexpected!(ss, Unexpected::Eof);
+                }
+                PeekResult::Bail(bail) => {
+                    self.state = TokenState::Skip(skip);
+                    return Err(ParseError::SourceBail(bail));
+                }
+            };
+
+            if let Some((end, reason)) = self.record_end() {
+                if character == end {
+                    self.state = TokenState::Skip(skip);
+                    return Err(ParseError::Unexpected(position, reason));
+                }
+            }
+            if let Some(comment) = skip.comment {
+                skip.comment = match comment.next(character) {
+                    _ if comment == Comment::Line && character == b'\n' => None,
+                    Some(Comment::End) => None,
+                    Some(comment) => Some(comment),
+                    None => {
+                        self.state = TokenState::Skip(skip);
+                        return Err(ParseError::Unexpected(Pos(position.0 - 1), Unexpected::Character));
+                    }
+                };
+            } else if let Some(quote) = skip.string {
+                match character {
+                    _ if skip.escape => skip.escape = false,
+                    b'\\' => skip.escape = true,
+                    _ if character == quote => skip.string = None,
+                    _ => (),
+                }
+            } else {
+                match character {
+                    b'/' if self.comments() => skip.comment = Some(Comment::Start),
+                    b'"' => skip.string = Some(character),
+                    b'\'' if self.json5() => skip.string = Some(character),
+                    b'{' | b'[' => {
+                        let depth = self.parser.depth() + self.skip_stack.len();
+                        if self.limits.max_depth.is_some_and(|max| depth >= max) {
+                            self.state = TokenState::Skip(skip);
+                            return Err(ParseError::Unexpected(position, Unexpected::DepthLimit));
+                        }
+                        self.skip_stack.push(if character == b'{' { b'}' } else { b']' });
+                    }
+                    b'}' | b']' => {
+                        if self.skip_stack.last() != Some(&character) {
+                            self.state = TokenState::Skip(skip);
+                            let reason = if character == b'}' { Unexpected::ObjectClose } else { Unexpected::ArrayClose };
+                            return Err(ParseError::Unexpected(position, reason));
+                        }
+                        self.skip_stack.pop();
+                    }
+                    _ => (),
+                }
+            }
+            ss.skip(1);
+
+            if skip.string.is_none() && skip.comment.is_none() && self.skip_stack.is_empty() {
+                self.state = TokenState::None;
+                return self.parser.finish_skip();
+            }
+        }
     }
 
     fn do_run<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail>
@@ -338,14 +849,55 @@ impl TokenizerState {
         loop {
             match self.state {
                 TokenState::String => self.do_str(ss)?,
-                TokenState::Number(start) => self.do_num(ss, start)?,
+                TokenState::Number(start, zero) => self.do_num(ss, start, zero)?,
+                TokenState::Hex(start) => self.do_hex(ss, start)?,
+                TokenState::Identifier(state) => self.do_identifier(ss, state)?,
+                TokenState::Literal(literal, matched) => self.do_literal(ss, literal, matched)?,
+                TokenState::Skip(skip) => self.do_skip(ss, skip)?,
                 TokenState::None => {
-                    self.skip_whitespace(ss)?;
+                    if self.parser.in_root_number() {
+                        self.end_root_number(ss)?;
+                    }
+                    let skipped = self.skip_whitespace(ss);
+                    if let Some((end, reason)) = self.record_end() {
+                        match (&skipped, ss.peek_char()) {
+                            (&Err(ParseError::Eof), _) =>
+                                return Err(ParseError::Unexpected(ss.position(), reason)),
+                            (&Ok(()), PeekResult::Ok(character)) if character == end =>
+                                return Err(ParseError::Unexpected(ss.position(), reason)),
+                            _ => (),
+                        }
+                    }
+                    skipped?;
+                    let start = ss.position();
+                    self.check_document_size(start)?;
+
+                    if self.json5() && self.parser.expects_key() {
+                        if let PeekResult::Ok(character) = ss.peek_char() {
+                            if is_identifier_start(character) {
+                                self.string_start = start;
+                                self.string_kind = StringKind::Identifier;
+                                self.parser.token_string_delimiter(ss, StringKind::Identifier)?;
+                                self.state = TokenState::Identifier(utf8::UTF8_ACCEPT);
+                                continue;
+                            }
+                        }
+                    }
 
                     match self.read_char(ss)? {
-                        b'{' => self.parser.token_object_open(ss)?,
+                        b'{' => {
+                            self.parser.token_object_open(ss)?;
+                            if self.parser.skipping() {
+                                self.start_skip(b'}');
+                            }
+                        }
                         b'}' => self.parser.token_object_close(ss)?,
-                        b'[' => self.parser.token_array_open(ss)?,
+                        b'[' => {
+                            self.parser.token_array_open(ss)?;
+                            if self.parser.skipping() {
+                                self.start_skip(b']');
+                            }
+                        }
                         b']' => self.parser.token_array_close(ss)?,
                         b',' => self.parser.token_comma(ss)?,
                         b':' => self.parser.token_colon(ss)?,
@@ -353,37 +905,286 @@ impl TokenizerState {
                         b'.' => self.parser.token_dot(ss)?,
                         b'-' => self.parser.token_sign(ss, false)?,
                         b'+' => self.parser.token_sign(ss, true)?,
-                        b't' => {
-                            ss.skip(3);
-                            self.parser.token_bool(ss, true)?;
+                        b't' => self.do_literal(ss, Literal::True, 1)?,
+                        b'f' => self.do_literal(ss, Literal::False, 1)?,
+                        b'n' => self.do_literal(ss, Literal::Null, 1)?,
+                        b'I' if self.non_finite() => self.do_literal(ss, Literal::Infinity, 1)?,
+                        b'N' if self.non_finite() => self.do_literal(ss, Literal::NaN, 1)?,
+                        digit @ b'0'...b'9' => {
+                            let zero = digit == b'0';
+                            self.state = TokenState::Number(start, zero);
+                            self.do_num(ss, start, zero)?;
+                        }
+                        b'"' => self.start_string(ss, StringKind::DoubleQuoted)?,
+                        b'\'' if self.json5() => self.start_string(ss, StringKind::SingleQuoted)?,
+                        _ => return Err(ParseError::Unexpected(start, Unexpected::Character)),
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_number_length<SS>(&self, ss: &SS, start: Pos) -> PResult<(), SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        let digits = ss.position().0 - start.0;
+        if self.limits.max_number_length.is_some_and(|max| digits > max) {
+            return Err(ParseError::Unexpected(start, Unexpected::NumberLimit));
+        }
+        Ok(())
+    }
+
+    // Ends a root number at anything that can not be part of it. Root
+    // numbers are only allowed when the input after them is read anyway, so
+    // there is no need to wait for EOF.
+    fn end_root_number<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        let reason = match ss.peek_char() {
+            PeekResult::Ok(b'0'..=b'9') | PeekResult::Ok(b'.') | PeekResult::Ok(b'e')
+                | PeekResult::Ok(b'E') | PeekResult::Ok(b'+') | PeekResult::Ok(b'-') => return Ok(()),
+            PeekResult::Ok(b'I') | PeekResult::Ok(b'N') if self.non_finite() => return Ok(()),
+            PeekResult::Ok(character) if !self.ends_record(character) => Unexpected::Character,
+            PeekResult::Ok(_) | PeekResult::Eof =>
+                self.record_end().map_or(Unexpected::Eof, |(_, reason)| reason),
+            PeekResult::Bail(bail) => return Err(ParseError::SourceBail(bail)),
+        };
+        self.parser.end_root_number(ss, reason)
+    }
+
+    fn check_document_size<B>(&self, position: Pos) -> PResult<(), B> {
+        let start = self.document_start.0;
+        match self.limits.max_document_size {
+            Some(max) if position.0 - start > max =>
+                Err(ParseError::Unexpected(Pos(start + max), Unexpected::DocumentLimit)),
+            _ => Ok(()),
+        }
+    }
+
+    // Called when the source runs out of data, before it buffers more.
+    // Checks the limits against the token read so far, so that a single
+    // huge token can not grow the source without bounds.
+    fn check_pending<SS>(&self, ss: &SS) -> PResult<(), SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        self.check_document_size(ss.position())?;
+        match (self.state, self.string_state) {
+            (TokenState::String, StringState::None(_)) =>
+                self.parser.check_string_pending(ss.position().0 - self.string_start.0),
+            (TokenState::Identifier(_), _) =>
+                self.parser.check_string_pending(ss.position().0 - self.string_start.0),
+            (TokenState::Number(start, _), _) | (TokenState::Hex(start), _) => self.check_number_length(ss, start),
+            _ => Ok(()),
+        }
+    }
+
+    /// Earliest input position that may still be handed to the sink in a
+    /// `Range` on the next call to `run`. Sources are free to discard
+    /// everything before it. `None` means nothing before the current
+    /// position of the source is needed.
+    pub fn retain_from(&self) -> Option<Pos> {
+        let token = match self.state {
+            TokenState::None | TokenState::Literal(_, _) | TokenState::Skip(_) => None,
+            TokenState::String | TokenState::Identifier(_) => Some(self.string_start),
+            TokenState::Number(start, _) | TokenState::Hex(start) => Some(start),
+        };
+        // The sink gets the whole comment in a range.
+        let token = match self.comment {
+            Some(_) => Some(self.comment_start),
+            None => token,
+        };
+        match (token, self.parser.retain_from()) {
+            (Some(a), Some(b)) => Some(::std::cmp::min(a.0, b.0).into()),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// The tokens that would have been accepted in the current state.
+    /// After `run` returns an error, describes what was expected instead.
+    pub fn expected(&self) -> Expectation {
+        match self.comment {
+            Some(Comment::Start) =>
+                return Expectation::new(&[Expected::Byte(b'/'), Expected::Byte(b'*')], "in comment"),
+            Some(Comment::Block) | Some(Comment::BlockStar) =>
+                return Expectation::new(&[Expected::Byte(b'*')], "in comment"),
+            _ => (),
+        }
+        match self.state {
+            TokenState::String => {
+                let tokens = match self.string_state {
+                    StringState::StartEscape => Expected::Escape,
+                    StringState::UnicodeEscape(_, _, _) => Expected::HexDigit,
+                    StringState::StartUnicodeContinuation(StartContinuationState::Slash, _) => Expected::Byte(b'\\'),
+                    StringState::StartUnicodeContinuation(StartContinuationState::Uchar, _) => Expected::Byte(b'u'),
+                    StringState::None(_) | StringState::ZeroEscape | StringState::LineContinuation
+                        | StringState::End => match self.string_quote() {
+                            Some(b'\'') => Expected::Byte(b'\''),
+                            _ => Expected::Quote,
+                        },
+                };
+                Expectation::new(&[tokens], "in string")
+            }
+            TokenState::Literal(literal, matched) => {
+                let byte = literal.bytes()[matched as usize];
+                Expectation::new(&[Expected::Byte(byte)], "in literal")
+            }
+            TokenState::Hex(_) => Expectation::new(&[Expected::HexDigit], "in number"),
+            TokenState::Identifier(_) => Expectation::new(&[Expected::Colon], "in key"),
+            TokenState::Skip(skip) => {
+                let tokens = match self.skip_stack.last() {
+                    _ if skip.string == Some(b'\'') => Expected::Byte(b'\''),
+                    _ if skip.string.is_some() => Expected::Quote,
+                    Some(&b'}') => Expected::ObjectClose,
+                    _ => Expected::ArrayClose,
+                };
+                Expectation::new(&[tokens], "in skipped value")
+            }
+            TokenState::None | TokenState::Number(..) => self.parser.expected(),
+        }
+    }
+
+    pub fn run<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        match self.options.documents {
+            Documents::Single if self.options.strict => {
+                self.run_document(ss)?;
+                self.finish_trailing(ss)
+            }
+            Documents::Single => self.run_document(ss),
+            _ => self.run_documents(ss),
+        }
+    }
+
+    // Checks that only whitespace, and comments if enabled, follow the
+    // root value.
+    fn finish_trailing<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        match self.skip_whitespace(ss) {
+            Ok(()) => unexpected!(ss, Unexpected::Character),
+            Err(ParseError::Eof) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Prepares for the next document.
+    fn reset_document(&mut self) {
+        self.state = TokenState::None;
+        self.parser = ParserState::with_options(self.options);
+        self.string_state = StringState::None(utf8::UTF8_ACCEPT);
+        self.skip_stack.clear();
+        self.comment = None;
+        self.document = DocumentState::Between(false);
+    }
+
+    fn run_documents<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail>
+        where SS: Source + Sink + Bailable
+    {
+        let documents = self.options.documents;
+        loop {
+            match self.document {
+                DocumentState::Between(_) => {
+                    let first = loop {
+                        if let Some(comment) = self.comment {
+                            if let Some((pos, reason)) = self.comment_between(ss, comment)? {
+                                // Recovered from like a document.
+                                self.document_start = self.comment_start;
+                                ss.start_document(self.document_start);
+                                self.document = DocumentState::Recovering(pos, reason);
+                                break None;
+                            }
                         }
-                        b'f' => {
-                            ss.skip(4);
-                            self.parser.token_bool(ss, false)?;
+                        match ss.peek_char() {
+                            PeekResult::Ok(b' ') | PeekResult::Ok(b'\t')
+                                | PeekResult::Ok(b'\r') | PeekResult::Ok(b'\n') => ss.skip(1),
+                            PeekResult::Ok(b'/') if self.comments() => {
+                                self.start_comment(ss);
+                                ss.skip(1);
+                            }
+                            PeekResult::Ok(RECORD_SEPARATOR) if documents == Documents::Sequence => {
+                                ss.skip(1);
+                                self.document = DocumentState::Between(true);
+                            }
+                            PeekResult::Ok(character) => break Some(character),
+                            PeekResult::Eof => return Ok(()),
+                            PeekResult::Bail(bail) => return Err(ParseError::SourceBail(bail)),
                         }
-                        b'n' => {
-                            ss.skip(3);
-                            self.parser.token_null(ss)?;
+                    };
+                    let first = match first {
+                        Some(first) => first,
+                        None => continue,
+                    };
+                    let separated = matches!(self.document, DocumentState::Between(true));
+                    self.document_start = ss.position();
+                    self.document_scalar = !matches!(first, b'{' | b'[' | b'"');
+                    self.document = if documents == Documents::Sequence && !separated {
+                        DocumentState::Recovering(self.document_start, Unexpected::Character)
+                    } else {
+                        DocumentState::Inside
+                    };
+                    ss.start_document(self.document_start);
+                }
+                DocumentState::Inside => match self.run_document(ss) {
+                    Ok(()) => self.document = DocumentState::After(ss.position()),
+                    // The end of the input cuts off the last record.
+                    Err(ParseError::Unexpected(pos, Unexpected::Eof)) if documents == Documents::Sequence =>
+                        self.document = DocumentState::Recovering(pos, Unexpected::Truncated),
+                    Err(ParseError::Unexpected(pos, reason)) if documents != Documents::Concatenated =>
+                        self.document = DocumentState::Recovering(pos, reason),
+                    Err(err) => return Err(err),
+                },
+                DocumentState::After(end) => {
+                    // Only whitespace may follow, up to the end of the record.
+                    let trailing = if documents == Documents::Concatenated { None } else { loop {
+                        if let Some(comment) = self.comment {
+                            if let Some(error) = self.comment_between(ss, comment)? {
+                                break Some(error);
+                            }
                         }
-                        b'0'...b'9' => {
-                            let start = ss.position().0 - 1;
-                            self.state = TokenState::Number(start.into());
-                            self.do_num(ss, start.into())?;
+                        match ss.peek_char() {
+                            PeekResult::Ok(b' ') | PeekResult::Ok(b'\t') | PeekResult::Ok(b'\r') => ss.skip(1),
+                            PeekResult::Ok(b'\n') if !self.lines() => ss.skip(1),
+                            PeekResult::Ok(b'/') if self.comments() => {
+                                self.start_comment(ss);
+                                ss.skip(1);
+                            }
+                            PeekResult::Ok(character) if self.ends_record(character) => break None,
+                            PeekResult::Ok(_) => break Some((ss.position(), Unexpected::Character)),
+                            PeekResult::Eof => break None,
+                            PeekResult::Bail(bail) => return Err(ParseError::SourceBail(bail)),
                         }
-                        b'"' => {
-                            self.string_start = ss.position();
-                            self.string_state = StringState::None(utf8::UTF8_ACCEPT);
-                            self.state = TokenState::String;
-                            self.parser.token_quote(ss)?;
+                    } };
+                    if let Some((pos, reason)) = trailing {
+                        self.document = DocumentState::Recovering(pos, reason);
+                        continue;
+                    }
+                    if documents == Documents::Sequence && self.document_scalar && ss.position() == end {
+                        self.document = DocumentState::Recovering(end, Unexpected::Truncated);
+                        continue;
+                    }
+                    let document = Range::new(self.document_start, end);
+                    self.reset_document();
+                    ss.end_document(document).map_err(ParseError::SourceBail)?;
+                }
+                DocumentState::Recovering(pos, reason) => {
+                    loop {
+                        match ss.peek_char() {
+                            PeekResult::Ok(character) if self.ends_record(character) => break,
+                            PeekResult::Ok(_) => ss.skip(1),
+                            PeekResult::Eof => break,
+                            PeekResult::Bail(bail) => return Err(ParseError::SourceBail(bail)),
                         }
-                        _ => return unexpected!(ss, Unexpected::Character),
                     }
+                    let document = Range::new(self.document_start, ss.position());
+                    self.reset_document();
+                    ss.document_error(document, pos, reason).map_err(ParseError::SourceBail)?;
                 }
             }
         }
     }
 
-    pub fn run<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail>
+    fn run_document<SS>(&mut self, ss: &mut SS) -> PResult<(), SS::Bail>
         where SS: Source + Sink + Bailable
     {
         self.parser.reentry(ss)?;
@@ -394,9 +1195,14 @@ impl TokenizerState {
             match self.do_run(ss) {
                 Ok(()) => unreachable!(),
                 Err(ParseError::End) => {
+                    self.check_document_size(ss.position())?;
                     self.parser.finish(ss)?;
                     Ok(())
                 }
+                Err(ParseError::SourceBail(bail)) => {
+                    self.check_pending(ss)?;
+                    Err(ParseError::SourceBail(bail))
+                }
                 Err(ParseError::Eof) => unexpected!(ss, Unexpected::Eof),
                 err => err,
             }
@@ -424,3 +1230,13 @@ impl TokenizerState {
         // unreachable!();
     }
 }
+
+// Characters that can start an unquoted key in JSON5. Non-ASCII characters
+// are all allowed.
+fn is_identifier_start(character: u8) -> bool {
+    character.is_ascii_alphabetic() || character == b'$' || character == b'_' || character >= 0x80
+}
+
+fn is_identifier_part(character: u8) -> bool {
+    character.is_ascii_alphanumeric() || character == b'$' || character == b'_'
+}
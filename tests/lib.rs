@@ -1,14 +1,29 @@
 extern crate iterative_json_parser;
-use iterative_json_parser::source::string::{VecSource, VecSourceB};
+use iterative_json_parser::source::string::{VecSource, VecSourceB, SliceSource};
+use iterative_json_parser::source::reader::ReaderSource;
+use iterative_json_parser::source::stream::StreamSource;
+use iterative_json_parser::push::{PushParser, Status};
+use iterative_json_parser::pull::{Events, Event, PullError};
+use iterative_json_parser::source::chunked::ChunkSource;
+use iterative_json_parser::{Unexpected, Decoder, Error, Options, Limits, Documents, Dialect, Expected, Expectation};
+use iterative_json_parser::{NumberData, NumberKind, NumberError, Decimal, Range, Pos, Window};
+use iterative_json_parser::report::Report;
 use iterative_json_parser::Parser;
 use iterative_json_parser::ParseError;
 use iterative_json_parser::input::{SourceSink, BailVariant};
-use iterative_json_parser::{Source, Sink};
+use iterative_json_parser::{Source, PeekResult, Sink, Bailable, Position, StringPosition, StringKind};
 
 use iterative_json_parser::sink::into_enum::{EnumSink, Json};
+use iterative_json_parser::sink::value::{Value, Map, ValueSink, ValueError, Number, DuplicateKeys};
+use iterative_json_parser::sink::borrowed::{BorrowedValue, BorrowedValueSink};
+use iterative_json_parser::sink::tape::{TapeSink, Kind};
+use iterative_json_parser::sink::pointer::{Pointer, PointerError, PointerSink, PointerBail};
+use iterative_json_parser::sink::query::{Query, QueryError, QuerySink, QueryBail};
+use iterative_json_parser::sink::path::{Path, PathSink, PathTracking};
+use std::borrow::Cow;
 
-fn parse_to_enum_inner<Src>(mut ss: SourceSink<Src, EnumSink>, print: bool) -> Result<Json, ParseError<BailVariant<Src::Bail, ()>>> where Src: Source {
-    let mut parser = Parser::new();
+fn parse_to_enum_inner<Src>(mut ss: SourceSink<Src, EnumSink>, options: Options, print: bool) -> Result<Json, ParseError<BailVariant<Src::Bail, ()>>> where Src: Source {
+    let mut parser = Parser::with_options(options);
     loop {
         match parser.run(&mut ss) {
             Ok(()) => {
@@ -30,7 +45,46 @@ fn parse_to_enum_inner<Src>(mut ss: SourceSink<Src, EnumSink>, print: bool) -> R
     };
 }
 
-fn parse_to_enum_full_inner(data_bytes: &[u8], print: bool) -> Result<Json, ParseError<BailVariant<(), ()>>> {
+fn parse_to_enum_reader(data_bytes: &[u8], chunk_size: usize) -> Result<Json, ParseError<BailVariant<(), ()>>> {
+    let mut ss = SourceSink {
+        source: ReaderSource::with_chunk_size(data_bytes, chunk_size),
+        sink: EnumSink::new_windowed(),
+    };
+    let mut parser = Parser::new();
+    loop {
+        match parser.run(&mut ss) {
+            Ok(()) => return Ok(ss.sink.to_result()),
+            Err(ParseError::SourceBail(BailVariant::Source(()))) => {
+                ss.source.fill().unwrap();
+            },
+            Err(ParseError::SourceBail(BailVariant::Sink(()))) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn parse_to_enum_stream(data_bytes: &[u8], chunk_size: usize, max_buffered: usize) -> Result<Json, ParseError<BailVariant<(), ()>>> {
+    let mut ss = SourceSink {
+        source: StreamSource::with_chunk_size(data_bytes, chunk_size),
+        sink: EnumSink::new_windowed(),
+    };
+    let mut parser = Parser::new();
+    loop {
+        assert!(ss.source.window().bytes.len() <= max_buffered);
+        match parser.run(&mut ss) {
+            Ok(()) => return Ok(ss.sink.to_result()),
+            Err(ParseError::SourceBail(BailVariant::Source(()))) => {
+                let keep = parser.retain_from().unwrap_or(ss.source.position());
+                ss.source.discard_before(keep);
+                ss.source.fill().unwrap();
+            },
+            Err(ParseError::SourceBail(BailVariant::Sink(()))) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn parse_to_enum_full_inner(data_bytes: &[u8], options: Options, print: bool) -> Result<Json, ParseError<BailVariant<(), ()>>> {
     if print {
         println!("== Nonbailing ==");
     }
@@ -38,7 +92,7 @@ fn parse_to_enum_full_inner(data_bytes: &[u8], print: bool) -> Result<Json, Pars
         source: VecSource::new(data_bytes.to_vec()),
         sink: EnumSink::new(data_bytes),
     };
-    let result = parse_to_enum_inner(ss, print);
+    let result = parse_to_enum_inner(ss, options, print);
 
     if print {
         println!("== Bailing ==");
@@ -47,23 +101,46 @@ fn parse_to_enum_full_inner(data_bytes: &[u8], print: bool) -> Result<Json, Pars
         source: VecSourceB::new(data_bytes.to_vec()),
         sink: EnumSink::new_bailing(data_bytes),
     };
-    let bailing_result = parse_to_enum_inner(bailing_ss, print);
+    let bailing_result = parse_to_enum_inner(bailing_ss, options, print);
 
     assert_eq!(bailing_result, result);
+
+    if print {
+        println!("== Borrowed ==");
+    }
+    let slice_ss = SourceSink {
+        source: SliceSource::new(data_bytes),
+        sink: EnumSink::new(data_bytes),
+    };
+    let slice_result = parse_to_enum_inner(slice_ss, options, print);
+
+    assert_eq!(slice_result, result);
+
+    if options == Options::default() {
+        if print {
+            println!("== Reader ==");
+        }
+        let reader_result = parse_to_enum_reader(data_bytes, 1);
+
+        assert_eq!(reader_result, result);
+    }
     return result;
 }
 
 fn parse_to_enum(data_bytes: &[u8]) -> Result<Json, ParseError<BailVariant<(), ()>>> {
-    parse_to_enum_full_inner(data_bytes, false)
+    parse_to_enum_full_inner(data_bytes, Options::default(), false)
+}
+fn parse_to_enum_strict(data_bytes: &[u8]) -> Result<Json, ParseError<BailVariant<(), ()>>> {
+    parse_to_enum_full_inner(data_bytes, Options::strict(), false)
 }
-fn parse_to_enum_print(data_bytes: &[u8], test_name: &str, expected: bool) -> Result<Json, ParseError<BailVariant<(), ()>>> {
-    let res = parse_to_enum_full_inner(data_bytes, false);
+fn parse_to_enum_print(data_bytes: &[u8], options: Options, test_name: &str, expected: bool) -> Result<Json, ParseError<BailVariant<(), ()>>> {
+    let res = parse_to_enum_full_inner(data_bytes, options, false);
     if res.is_ok() == expected {
         res
     } else {
         println!("");
         println!("{}", test_name);
-        parse_to_enum_full_inner(data_bytes, false)
+        parse_to_enum_full_inner(data_bytes, options, false)
     }
 }
 
@@ -142,6 +219,152 @@ fn numbers() {
 //    assert_eq!(result, Ok(expected));
 //}
 
+/// Reading from a `Read` should give the same result regardless of how the
+/// input is split into chunks.
+#[test]
+fn reader_chunk_sizes() {
+    let input = r#"{"foo": [null, true, false], "bar": -1.23e-7, "baz": "w\u00f8o\""}"#;
+    let expected = parse_to_enum(input.as_bytes());
+    assert!(expected.is_ok());
+
+    for chunk_size in 1..(input.len() + 2) {
+        let result = parse_to_enum_reader(input.as_bytes(), chunk_size);
+        assert_eq!(result, expected);
+    }
+}
+
+/// Input that has been consumed is discarded, so only the token being read
+/// and the last chunk are held in memory.
+#[test]
+fn stream_source_discards_input() {
+    let input = r#"{"foo": [null, true, false], "bar": -1.23e-7, "baz": "w\u00f8o\""}"#;
+    let expected = parse_to_enum(input.as_bytes());
+    assert!(expected.is_ok());
+
+    for chunk_size in 1..(input.len() + 2) {
+        let result = parse_to_enum_stream(input.as_bytes(), chunk_size, input.len() + chunk_size);
+        assert_eq!(result, expected);
+    }
+
+    let mut input = "[".to_owned();
+    for _ in 0..10000 {
+        input.push_str("\"abcdefgh\", 12.5e3, ");
+    }
+    input.push_str("null]");
+    let expected = parse_to_enum(input.as_bytes());
+    assert!(expected.is_ok());
+    assert_eq!(parse_to_enum_stream(input.as_bytes(), 16, 32), expected);
+}
+
+// A source implementing only the required methods, so without a window.
+struct Bytes<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+impl<'a> Bailable for Bytes<'a> {
+    type Bail = ();
+}
+impl<'a> Source for Bytes<'a> {
+    fn position(&self) -> Pos {
+        self.pos.into()
+    }
+    fn skip(&mut self, num: usize) {
+        self.pos += num;
+    }
+    fn peek_char(&mut self) -> PeekResult<()> {
+        match self.bytes.get(self.pos) {
+            Some(&character) => PeekResult::Ok(character),
+            None => PeekResult::Eof,
+        }
+    }
+    fn peek_slice(&self, length: usize) -> Option<&[u8]> {
+        self.bytes.get(self.pos..(self.pos + length))
+    }
+}
+
+/// Sources written before `Source::window` existed still work, with sinks
+/// that hold on to the input themselves.
+#[test]
+fn source_without_window() {
+    let input = br#"{"a": [1, "b\n", null]}"#;
+    let mut ss = SourceSink {
+        source: Bytes { bytes: input, pos: 0 },
+        sink: BorrowedValueSink::new(input),
+    };
+    Parser::new().run(&mut ss).unwrap();
+    assert_eq!(ss.sink.into_value(), Decoder::new().decode_borrowed(input).ok());
+
+    // The default window holds nothing.
+    let mut ss = SourceSink {
+        source: Bytes { bytes: br#"["a"]"#, pos: 0 },
+        sink: ValueSink::new(),
+    };
+    match Parser::new().run(&mut ss) {
+        Err(ParseError::SourceBail(BailVariant::Sink(ValueError::Unavailable))) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+/// Feeding chunks should emit the same value no matter where the chunk
+/// boundaries are.
+#[test]
+fn push_parser_chunks() {
+    let input = br#"{"foo": [null, true, false], "bar": -1.23e-7, "baz": "w\u00f8o\""}"#;
+    let expected = parse_to_enum(input);
+    assert!(expected.is_ok());
+
+    for chunk_size in 1..(input.len() + 1) {
+        let mut parser = PushParser::new(EnumSink::new_windowed());
+        let mut status = Status::NeedMoreData;
+        for chunk in input.chunks(chunk_size) {
+            status = parser.feed(chunk).unwrap();
+        }
+        assert_eq!(status, Status::Done);
+        parser.finish().unwrap();
+        assert_eq!(Ok(parser.into_sink().to_result()), expected);
+    }
+}
+
+/// A number at the root is only complete once we know no more input follows.
+#[test]
+fn push_parser_finish() {
+    let mut parser = PushParser::new(EnumSink::new_windowed());
+    assert_eq!(parser.feed(b"[12").unwrap(), Status::NeedMoreData);
+    assert_eq!(parser.feed(b"34").unwrap(), Status::NeedMoreData);
+    assert_eq!(parser.finish(), Err(ParseError::Unexpected(5.into(), Unexpected::Eof)));
+
+    let mut parser = PushParser::new(EnumSink::new_windowed());
+    assert_eq!(parser.feed(b"[12").unwrap(), Status::NeedMoreData);
+    assert_eq!(parser.feed(b"34]").unwrap(), Status::Done);
+    parser.finish().unwrap();
+    assert_eq!(parser.into_sink().to_result(), a![n!("+1234.0e+1")]);
+}
+
+#[test]
+fn decoder() {
+    let input = br#"{"foo": [null, true, false], "bar": -1.23e-7, "baz": "woo\""}"#;
+    let expected = parse_to_enum(input).unwrap();
+
+    assert_eq!(Decoder::parse_slice(input).unwrap(), expected);
+    assert_eq!(Decoder::parse_reader(&input[..]).unwrap(), expected);
+
+    match Decoder::parse_slice(b"[1, }") {
+        Err(Error::Unexpected(pos, Unexpected::ObjectClose)) => assert_eq!(pos, 4.into()),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    match Decoder::parse_reader(&b"[1, "[..]) {
+        Err(Error::Unexpected(_, Unexpected::Eof)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    let mut sink = EnumSink::new_bailing(input);
+    match Decoder::new().decode_with(input, &mut sink) {
+        Err(Error::Sink(())) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    assert_eq!(sink.stack, vec![o!{}, s!("foo")]);
+}
+
 /// Test a more complete example with many types.
 #[test]
 fn full_parse() {
@@ -355,6 +578,1543 @@ const JSON_TEST_SUITE_ACCEPTABLE_FAILS: [&'static str; 25] = [
     "n_number_+1.json",
 ];
 
+#[test]
+fn strict_rejects_extensions() {
+    let cases: [(&str, Unexpected); 10] = [
+        ("[1, 2,]", Unexpected::TrailingComma),
+        ("[[],]", Unexpected::TrailingComma),
+        ("{\"a\": 1,}", Unexpected::TrailingComma),
+        ("{\"a\": {},}", Unexpected::TrailingComma),
+        ("[+1]", Unexpected::PlusSign),
+        ("[012]", Unexpected::LeadingZero),
+        ("[-00.5]", Unexpected::LeadingZero),
+        ("[-007]", Unexpected::LeadingZero),
+        ("[1.]", Unexpected::EmptyFraction),
+        ("[1.e5]", Unexpected::EmptyFraction),
+    ];
+    for &(input, reason) in cases.iter() {
+        match parse_to_enum_strict(input.as_bytes()) {
+            Err(ParseError::Unexpected(_, actual)) => assert_eq!(actual, reason, "{}", input),
+            res => panic!("{}: unexpected result {:?}", input, res),
+        }
+        assert!(parse_to_enum(input.as_bytes()).is_ok() || reason == Unexpected::EmptyFraction);
+
+        // The checks do not depend on the source's window.
+        let mut ss = SourceSink {
+            source: Bytes { bytes: input.as_bytes(), pos: 0 },
+            sink: BorrowedValueSink::new(input.as_bytes()),
+        };
+        match Parser::with_options(Options::strict()).run(&mut ss) {
+            Err(ParseError::Unexpected(_, actual)) => assert_eq!(actual, reason, "{}", input),
+            res => panic!("{}: unexpected result {:?}", input, res),
+        }
+    }
+
+    match parse_to_enum_strict(b"[1e]") {
+        Err(ParseError::Unexpected(_, Unexpected::EmptyExponent)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+
+    assert_eq!(parse_to_enum_strict(b"[[], {}, 0, -0.5, 10e+05]"), parse_to_enum(b"[[], {}, 0, -0.5, 10e+05]"));
+}
+
+/// Only whitespace may follow the root value in strict mode, which makes
+/// scalar roots possible.
+#[test]
+fn strict_root_values() {
+    let strict = |input: &str| parse_to_enum_full_inner(input.as_bytes(), Options::strict(), false);
+
+    let cases: [(&str, usize); 6] = [
+        ("[1] xyz", 4),
+        ("{\"a\":1}}", 7),
+        ("[1]]", 3),
+        ("[1] // c", 4),
+        ("1 2", 2),
+        ("\"x\"\"y\"", 3),
+    ];
+    for &(input, pos) in cases.iter() {
+        match strict(input) {
+            Err(ParseError::Unexpected(actual, Unexpected::Character)) => assert_eq!(actual, Pos(pos), "{}", input),
+            res => panic!("{}: unexpected result {:?}", input, res),
+        }
+    }
+
+    assert_eq!(strict("\"x\""), Ok(Json::String("x".to_string())));
+    assert_eq!(strict(" true \n"), Ok(Json::Boolean(true)));
+    assert_eq!(strict("null"), Ok(Json::Null));
+    assert_eq!(strict("-12.5 "), parse_to_enum(b"[-12.5]").map(|json| match json {
+        Json::Array(mut values) => values.remove(0),
+        json => json,
+    }));
+    match strict("1.") {
+        Err(ParseError::Unexpected(_, Unexpected::EmptyFraction)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+
+    let comments = Options { comments: true, ..Options::strict() };
+    assert!(parse_to_enum_full_inner(b"[1] // c", comments, false).is_ok());
+    assert!(parse_to_enum_full_inner(b"1 /* c */", comments, false).is_ok());
+    match parse_to_enum_full_inner(b"[1] /* c", comments, false) {
+        Err(ParseError::Unexpected(Pos(8), Unexpected::Eof)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+
+    // Outside of strict mode, parsing stops after the root value.
+    assert!(parse_to_enum(b"[1] xyz").is_ok());
+}
+
+/// Literals are matched byte by byte, the error points at the first byte
+/// that does not match.
+#[test]
+fn invalid_literals() {
+    let cases: [(&str, usize); 5] = [
+        ("[txyz]", 2),
+        ("[fals!]", 5),
+        ("[nope]", 2),
+        ("{\"a\": nul}", 9),
+        ("[trUe]", 3),
+    ];
+    for &(input, pos) in cases.iter() {
+        assert_eq!(parse_to_enum(input.as_bytes()),
+                   Err(ParseError::Unexpected(pos.into(), Unexpected::InvalidLiteral)), "{}", input);
+    }
+
+    assert_eq!(parse_to_enum(b"[tru"), Err(ParseError::Unexpected(4.into(), Unexpected::Eof)));
+    assert_eq!(parse_to_enum(b"[true, false, null]"), Ok(a![v!(true), v!(false), v!(null)]));
+}
+
+fn report_error(input: &[u8], options: Options) -> Report {
+    let mut ss = SourceSink {
+        source: SliceSource::new(input),
+        sink: EnumSink::new(input),
+    };
+    let mut parser = Parser::with_options(options);
+    match parser.run(&mut ss) {
+        Err(ParseError::Unexpected(pos, reason)) => Report::new(input, pos, reason, parser.expected()),
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn error_report() {
+    let input = b"{\n  \"a\": [1, 2],\n  \"b\": true]\n}";
+    let report = report_error(input, Options::default());
+    assert_eq!((report.line, report.column), (3, 12));
+    assert_eq!(report.expected.tokens, vec![Expected::Comma, Expected::ObjectClose]);
+    assert_eq!(report.to_string(), "\
+line 3, column 12: expected ',' or '}' after object value, found ']'
+  |
+3 |   \"b\": true]
+  |            ^");
+
+    let report = report_error(b"[1, 012]", Options::strict());
+    assert_eq!((report.line, report.column), (1, 5));
+    assert_eq!(report.message(), "unexpected leading zero in number");
+
+    let report = report_error(b"[\"\xc3\xa6\", trux]", Options::default());
+    assert_eq!((report.line, report.column), (1, 10));
+    assert_eq!(report.message(), "expected 'e' in literal, found 'x'");
+
+    // Invalid UTF-8 is shown like `String::from_utf8_lossy` does, and the
+    // column counts it the same way.
+    let input = b"[\"\x82\x82\", \"\xe2\x82\", trux]";
+    let report = Report::new(input, Pos(15), Unexpected::Character, Expectation::new(&[], "in literal"));
+    assert_eq!((report.line, report.column), (1, 15));
+    assert!(report.to_string().ends_with("\
+1 | [\"\u{fffd}\u{fffd}\", \"\u{fffd}\", trux]
+  |               ^"), "{}", report);
+    let report = Report::new(input, Pos(8), Unexpected::InvalidUtf8, Expectation::new(&[], "in string"));
+    assert_eq!(report.column, 9);
+
+    let report = report_error(b"[1.5", Options::default());
+    assert_eq!(report.message(), "expected 'e', ',' or ']' in number, found end of input");
+}
+
+#[test]
+fn depth_limit() {
+    let options = Options {
+        limits: Limits { max_depth: Some(3), ..Limits::default() },
+        ..Options::default()
+    };
+    assert!(parse_to_enum_full_inner(b"[{\"a\": [1]}, [[]]]", options, false).is_ok());
+    assert_eq!(parse_to_enum_full_inner(b"[{\"a\": [[1]]}]", options, false),
+               Err(ParseError::Unexpected(8.into(), Unexpected::DepthLimit)));
+    assert_eq!(parse_to_enum_full_inner(b"[[[{}]]]", options, false),
+               Err(ParseError::Unexpected(3.into(), Unexpected::DepthLimit)));
+
+    let deep = vec![b'['; 100000];
+    let options = Options {
+        limits: Limits { max_depth: Some(128), ..Limits::default() },
+        ..Options::default()
+    };
+    assert_eq!(parse_to_enum_full_inner(&deep, options, false),
+               Err(ParseError::Unexpected(128.into(), Unexpected::DepthLimit)));
+}
+
+#[test]
+fn size_limits() {
+    let limits = |limits: Limits| Options { limits, ..Options::default() };
+
+    let options = limits(Limits { max_string_length: Some(4), ..Limits::default() });
+    assert!(parse_to_enum_full_inner(b"[\"abcd\", \"a\\n\\u00e9\"]", options, false).is_ok());
+    assert_eq!(parse_to_enum_full_inner(b"[\"abcde\"]", options, false),
+               Err(ParseError::Unexpected(1.into(), Unexpected::StringLimit)));
+    assert_eq!(parse_to_enum_full_inner(b"{\"ab\\ncd\": 1}", options, false),
+               Err(ParseError::Unexpected(1.into(), Unexpected::StringLimit)));
+
+    let options = limits(Limits { max_number_length: Some(3), ..Limits::default() });
+    assert!(parse_to_enum_full_inner(b"[-123.456e789]", options, false).is_ok());
+    assert_eq!(parse_to_enum_full_inner(b"[1, 1234]", options, false),
+               Err(ParseError::Unexpected(4.into(), Unexpected::NumberLimit)));
+    assert_eq!(parse_to_enum_full_inner(b"[1.0001]", options, false),
+               Err(ParseError::Unexpected(3.into(), Unexpected::NumberLimit)));
+
+    let options = limits(Limits { max_members: Some(2), ..Limits::default() });
+    assert!(parse_to_enum_full_inner(b"[[1, 2], {\"a\": [], \"b\": {}}]", options, false).is_ok());
+    assert_eq!(parse_to_enum_full_inner(b"[1, [2, 3], 4]", options, false),
+               Err(ParseError::Unexpected(12.into(), Unexpected::MemberLimit)));
+    assert_eq!(parse_to_enum_full_inner(b"{\"a\": 1, \"b\": 2, \"c\": 3}", options, false),
+               Err(ParseError::Unexpected(17.into(), Unexpected::MemberLimit)));
+
+    let options = limits(Limits { max_document_size: Some(8), ..Limits::default() });
+    assert!(parse_to_enum_full_inner(b"[1, 2.5]", options, false).is_ok());
+    assert_eq!(parse_to_enum_full_inner(b"[1, 2.55]", options, false),
+               Err(ParseError::Unexpected(8.into(), Unexpected::DocumentLimit)));
+    assert_eq!(parse_to_enum_full_inner(b"[\"abcdefghij\"]", options, false),
+               Err(ParseError::Unexpected(8.into(), Unexpected::DocumentLimit)));
+}
+
+/// A source that runs out of data checks the limits before buffering more,
+/// so an endless token fails instead of growing the buffer forever.
+#[test]
+fn size_limits_endless_token() {
+    use std::io::Read;
+
+    let limits = |limits: Limits| Options { limits, ..Options::default() };
+
+    let endless = std::io::Cursor::new(b"[\"".to_vec()).chain(std::io::repeat(b'a'));
+    let decoder = Decoder::with_options(limits(Limits { max_string_length: Some(1 << 16), ..Limits::default() }));
+    match decoder.decode_reader(endless) {
+        Err(Error::Unexpected(pos, Unexpected::StringLimit)) => assert_eq!(pos, 1.into()),
+        other => panic!("{:?}", other),
+    }
+
+    let endless = std::io::Cursor::new(b"[1.".to_vec()).chain(std::io::repeat(b'0'));
+    let decoder = Decoder::with_options(limits(Limits { max_number_length: Some(1 << 16), ..Limits::default() }));
+    match decoder.decode_reader(endless) {
+        Err(Error::Unexpected(pos, Unexpected::NumberLimit)) => assert_eq!(pos, 3.into()),
+        other => panic!("{:?}", other),
+    }
+
+    let endless = std::io::Cursor::new(b"[".to_vec()).chain(std::io::repeat(b' '));
+    let decoder = Decoder::with_options(limits(Limits { max_document_size: Some(1 << 16), ..Limits::default() }));
+    match decoder.decode_reader(endless) {
+        Err(Error::Unexpected(pos, Unexpected::DocumentLimit)) => assert_eq!(pos, (1 << 16).into()),
+        other => panic!("{:?}", other),
+    }
+}
+
+/// The parts of a number, as the tokenizer reads them.
+fn number_data(text: &str) -> NumberData {
+    let options = Options { documents: Documents::Concatenated, ..Options::default() };
+    match Events::with_options(SliceSource::new(text.as_bytes()), options).next() {
+        Some(Ok(Event::Number(number))) => number,
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn number_conversions() {
+    let f64_of = |text: &str| number_data(text).to_f64(text.as_bytes().into());
+    let i64_of = |text: &str| number_data(text).to_i64(text.as_bytes().into());
+    let u64_of = |text: &str| number_data(text).to_u64(text.as_bytes().into());
+    let i128_of = |text: &str| number_data(text).to_i128(text.as_bytes().into());
+
+    for text in ["0", "-0", "1.5", "-12.5e+1", "0.1", "1e-400", "2.2250738585072011e-308",
+                 "9007199254740993", "179769313486231570000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000"].iter() {
+        assert_eq!(f64_of(text), Ok(text.parse::<f64>().unwrap()), "{}", text);
+    }
+    assert_eq!(f64_of("9007199254740993"), Ok(9007199254740992.0));
+    assert_eq!(f64_of("1e309"), Err(NumberError::OutOfRange));
+    assert_eq!(f64_of("-1e309"), Err(NumberError::OutOfRange));
+
+    assert_eq!(i64_of("0"), Ok(0));
+    assert_eq!(i64_of("-0.0"), Ok(0));
+    assert_eq!(i64_of("1.5e1"), Ok(15));
+    assert_eq!(i64_of("1500e-2"), Ok(15));
+    assert_eq!(i64_of("0.0e99999999999999999999"), Ok(0));
+    assert_eq!(i64_of("9223372036854775807"), Ok(i64::MAX));
+    assert_eq!(i64_of("-9223372036854775808"), Ok(i64::MIN));
+    assert_eq!(i64_of("9223372036854775808"), Err(NumberError::OutOfRange));
+    assert_eq!(i64_of("-9223372036854775809"), Err(NumberError::OutOfRange));
+    assert_eq!(i64_of("1.5"), Err(NumberError::Fractional));
+    assert_eq!(i64_of("1e-99999999999999999999"), Err(NumberError::Fractional));
+    assert_eq!(i64_of("1e99999999999999999999"), Err(NumberError::OutOfRange));
+    assert_eq!(i64_of("1.5e99999999999999999999"), Err(NumberError::OutOfRange));
+    assert_eq!(i64_of("1.5e-99999999999999999999"), Err(NumberError::Fractional));
+
+    assert_eq!(u64_of("18446744073709551615"), Ok(u64::MAX));
+    assert_eq!(u64_of("18446744073709551616"), Err(NumberError::OutOfRange));
+    assert_eq!(u64_of("-0"), Ok(0));
+    assert_eq!(u64_of("-1"), Err(NumberError::OutOfRange));
+
+    assert_eq!(i128_of("-170141183460469231731687303715884105728"), Ok(i128::MIN));
+    assert_eq!(i128_of("1.70141183460469231731687303715884105727e38"), Ok(i128::MAX));
+    assert_eq!(i128_of("170141183460469231731687303715884105728"), Err(NumberError::OutOfRange));
+
+    let number = number_data("12");
+    assert_eq!(number.to_i64(b"1".as_ref().into()), Err(NumberError::Unavailable));
+}
+
+fn decimal(text: &str) -> Decimal {
+    number_data(text).to_decimal(text.as_bytes().into()).unwrap()
+}
+
+#[test]
+fn decimals() {
+    let input = b"[12345678901234567890.123456789, -0.00, 1.50, 15e-1, 0.000001, 1e-7, 12e3, -2.5E+10]";
+    let sink = Decoder::new().decode_with(input, EnumSink::new(input).with_decimals()).unwrap();
+    let numbers = match sink.to_result() {
+        Json::Array(values) => values,
+        other => panic!("{:?}", other),
+    };
+    let formatted: Vec<String> = numbers.iter().map(|value| match *value {
+        Json::Decimal(ref decimal) => decimal.to_string(),
+        ref other => panic!("{:?}", other),
+    }).collect();
+    assert_eq!(formatted, ["12345678901234567890.123456789", "-0.00", "1.50", "1.5", "0.000001",
+                           "1e-7", "1.2e+4", "-2.5e+10"]);
+
+    let number = decimal("12345678901234567890.123456789");
+    assert_eq!(number.digits(), b"12345678901234567890123456789");
+    assert_eq!(number.scale(), 9);
+    assert!(!number.is_negative());
+
+    let mut number = decimal("-0012.3400e1");
+    assert_eq!((number.digits(), number.scale()), (&b"123400"[..], 3));
+    number.normalize();
+    assert_eq!((number.digits(), number.scale()), (&b"1234"[..], 1));
+    assert_eq!(number.to_string(), "-123.4");
+    assert_eq!(decimal("1200").normalized().scale(), -2);
+    assert_eq!(decimal("-0.0").normalized().to_string(), "0");
+
+    assert_eq!(decimal("1.50"), decimal("15e-1"));
+    assert_eq!(decimal("-0"), decimal("0.000"));
+    let ascending = ["-1e20", "-12.5", "-12.4999999999999999999", "-0.001", "0", "1e-300",
+                     "0.1", "1", "1.000000000000000000001", "99", "1e2", "100.5"];
+    for pair in ascending.windows(2) {
+        assert!(decimal(pair[0]) < decimal(pair[1]), "{} < {}", pair[0], pair[1]);
+        assert!(decimal(pair[1]) > decimal(pair[0]), "{} > {}", pair[1], pair[0]);
+    }
+
+    for huge in ["1e99999999999999999999", "1.5e99999999999999999999", "1.5e-9223372036854775807"].iter() {
+        assert_eq!(number_data(huge).to_decimal(huge.as_bytes().into()), Err(NumberError::OutOfRange), "{}", huge);
+    }
+    assert_eq!(decimal("1.5e9223372036854775807").scale(), -9223372036854775806);
+
+    // The sink falls back to the string form.
+    let input = b"[1e99999999999999999999]";
+    let sink = Decoder::new().decode_with(input, EnumSink::new(input).with_decimals()).unwrap();
+    assert_eq!(sink.to_result(), Json::Array(vec![Json::Number("+1.0e+99999999999999999999".to_string())]));
+}
+
+#[test]
+fn value_sink() {
+    let input = br#"{"name": "caf\u00e9", "id": 42, "big": 18446744073709551615, "neg": -7,
+                    "ratio": 0.25, "exp": 1e2, "tags": ["a", null, true], "z": {}, "a": []}"#;
+    let value = Decoder::new().decode_value(input).unwrap();
+
+    assert_eq!(value["name"].as_str(), Some("caf\u{e9}"));
+    assert_eq!(value["id"], Value::Number(Number::Integer(42)));
+    assert_eq!(value["big"].as_number(), Some(Number::Unsigned(u64::MAX)));
+    assert_eq!(value["big"].as_i64(), None);
+    assert_eq!(value["neg"].as_i64(), Some(-7));
+    assert_eq!(value["neg"].as_u64(), None);
+    assert_eq!(value["ratio"].as_number(), Some(Number::Float(0.25)));
+    assert_eq!(value["exp"].as_number(), Some(Number::Float(100.0)));
+    assert_eq!(value["tags"][2].as_bool(), Some(true));
+    assert!(value["tags"][1].is_null());
+
+    // Misses give null instead of panicking.
+    assert!(value["missing"].is_null());
+    assert!(value["tags"][3].is_null());
+    assert!(value["id"]["deeper"][0].is_null());
+    assert_eq!(value.get("missing"), None);
+
+    let map = value.as_object().unwrap();
+    let keys: Vec<&str> = map.keys().collect();
+    assert_eq!(keys, ["name", "id", "big", "neg", "ratio", "exp", "tags", "z", "a"]);
+    assert_eq!(map.len(), 9);
+    assert!(map["z"].as_object().unwrap().is_empty());
+
+    let from_reader = Decoder::new().decode_value_reader(&input[..]).unwrap();
+    assert_eq!(from_reader, value);
+
+    assert_eq!(Decoder::new().decode_value(b"[1e400]").unwrap_err().to_string(),
+               "aborted by sink: Number(OutOfRange)");
+}
+
+#[test]
+fn value_sink_duplicate_keys() {
+    let input = br#"{"a": 1, "b": 2, "a": 3}"#;
+    let decode = |policy| {
+        Decoder::new().decode_with(input, ValueSink::with_duplicate_keys(policy))
+            .map(|sink| sink.into_value().unwrap())
+    };
+
+    let value = decode(DuplicateKeys::Last).unwrap();
+    let members: Vec<(&str, i64)> = value.as_object().unwrap().iter()
+        .map(|(key, value)| (&key[..], value.as_i64().unwrap()))
+        .collect();
+    assert_eq!(members, [("a", 3), ("b", 2)]);
+
+    let value = decode(DuplicateKeys::First).unwrap();
+    assert_eq!(value["a"].as_i64(), Some(1));
+    assert_eq!(value.as_object().unwrap().len(), 2);
+
+    match decode(DuplicateKeys::Reject) {
+        Err(Error::Sink(ValueError::DuplicateKey(key))) => assert_eq!(key, "a"),
+        other => panic!("{:?}", other),
+    }
+    // Keys only clash within the same object.
+    assert!(Decoder::new().decode_with(br#"{"a": {"a": 1}, "b": [{"a": 2}]}"#,
+                                       ValueSink::with_duplicate_keys(DuplicateKeys::Reject)).is_ok());
+}
+
+/// Lookups work the same for small maps, which are searched in order, and
+/// larger ones, which have an index.
+#[test]
+fn map_lookup() {
+    let int = |i| Value::Number(Number::Integer(i));
+    let mut map: Map = Map::new();
+    for i in 0..100 {
+        assert_eq!(map.insert(format!("k{}", i), int(i)), None);
+        assert_eq!(map.len(), i as usize + 1);
+        for j in 0..(i + 1) {
+            assert_eq!(map.get(&format!("k{}", j)).and_then(Value::as_i64), Some(j), "{} of {}", j, i);
+        }
+        assert!(!map.contains_key(&format!("k{}", i + 1)));
+    }
+    assert_eq!(map.insert("k5".to_owned(), Value::Null), Some(int(5)));
+    assert_eq!(map.insert("k50".to_owned(), Value::Null), Some(int(50)));
+    *map.get_mut("k99").unwrap() = Value::Bool(true);
+    assert_eq!(map.len(), 100);
+    assert!(map["k5"].is_null() && map["k50"].is_null() && map["missing"].is_null());
+    assert_eq!(map["k99"], Value::Bool(true));
+    assert_eq!(map.keys().nth(50), Some("k50"));
+
+    let mut small: Map = Map::new();
+    small.insert("b".to_owned(), Value::Null);
+    small.insert("a".to_owned(), Value::Bool(false));
+    assert_eq!(format!("{:?}", small), r#"{"b": Null, "a": Bool(false)}"#);
+}
+
+#[test]
+fn borrowed_value_sink() {
+    let input = br#"{"plain": "text", "esc\naped": "a\"b", "unicode": "\u00e9t\u00e9", "empty": "",
+                    "list": [1, -2.5, "x", null]}"#;
+    let value = Decoder::new().decode_borrowed(input).unwrap();
+
+    let is_borrowed = |string: &Cow<str>| match *string {
+        Cow::Borrowed(_) => true,
+        Cow::Owned(_) => false,
+    };
+    fn string<'a>(value: &BorrowedValue<'a>) -> Cow<'a, str> {
+        match *value {
+            BorrowedValue::String(ref string) => string.clone(),
+            ref other => panic!("{:?}", other),
+        }
+    }
+
+    assert_eq!(string(&value["plain"]), "text");
+    assert!(is_borrowed(&string(&value["plain"])));
+    assert_eq!(string(&value["esc\naped"]), "a\"b");
+    assert!(!is_borrowed(&string(&value["esc\naped"])));
+    assert_eq!(value["unicode"].as_str(), Some("\u{e9}t\u{e9}"));
+    assert!(is_borrowed(&string(&value["empty"])));
+    assert_eq!(value["list"][1].as_f64(), Some(-2.5));
+    assert!(value["list"][3].is_null());
+    assert!(value["list"][4].is_null());
+
+    let keys: Vec<bool> = value.as_object().unwrap().iter().map(|(key, _)| is_borrowed(key)).collect();
+    assert_eq!(keys, [true, false, true, true, true]);
+
+    // Same semantics as the owned tree.
+    let owned = Decoder::new().decode_value(input).unwrap();
+    assert_eq!(value.as_object().unwrap().keys().collect::<Vec<_>>(),
+               owned.as_object().unwrap().keys().collect::<Vec<_>>());
+    assert_eq!(owned["esc\naped"], Value::String("a\"b".to_owned()));
+
+    let input = br#"{"a": 1, "a": 2}"#;
+    match Decoder::new().decode_with(input, BorrowedValueSink::with_duplicate_keys(input, DuplicateKeys::Reject)) {
+        Err(Error::Sink(ValueError::DuplicateKey(key))) => assert_eq!(key, "a"),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn tape_sink() {
+    let input = br#"{"a": [1, {"deep": [[], {}]}, "x\ty"], "b": -2.5, "c": "plain", "d": [true, false, null],
+                    "e": 18446744073709551615}"#;
+    let tape = Decoder::new().decode_tape(input).unwrap();
+    let root = tape.root().unwrap();
+    assert_eq!(root.kind(), Kind::Object);
+    assert_eq!(root.end(), tape.words().len());
+
+    let keys: Vec<&str> = root.members().map(|(key, _)| key).collect();
+    assert_eq!(keys, ["a", "b", "c", "d", "e"]);
+
+    let a = root.get("a").unwrap();
+    assert_eq!(a.kind(), Kind::Array);
+    assert_eq!(a.children().count(), 3);
+    assert_eq!(a.get_at(0).unwrap().as_number(), Some(Number::Integer(1)));
+    assert_eq!(a.get_at(2).unwrap().as_str(), Some("x\ty"));
+    assert!(a.get_at(3).is_none());
+
+    // Skipping a subtree jumps straight past its end.
+    let nested = a.get_at(1).unwrap();
+    let after = a.get_at(2).unwrap();
+    assert_eq!(nested.end(), after.index());
+    let deep = nested.get("deep").unwrap();
+    assert_eq!(deep.children().map(|child| child.kind()).collect::<Vec<_>>(), [Kind::Array, Kind::Object]);
+    assert_eq!(deep.get_at(0).unwrap().children().count(), 0);
+
+    assert_eq!(root.get("b").unwrap().as_number(), Some(Number::Float(-2.5)));
+    assert_eq!(root.get("c").unwrap().as_str(), Some("plain"));
+    assert_eq!(root.get("e").unwrap().as_number(), Some(Number::Unsigned(u64::MAX)));
+    let d: Vec<Kind> = root.get("d").unwrap().children().map(|child| child.kind()).collect();
+    assert_eq!(d, [Kind::Bool, Kind::Bool, Kind::Null]);
+    assert_eq!(root.get("d").unwrap().get_at(1).unwrap().as_bool(), Some(false));
+    assert!(root.get("missing").is_none());
+    assert!(root.get("c").unwrap().get("x").is_none());
+
+    // Without the input, every string goes into the side buffer.
+    let windowed = Decoder::new().decode_reader_with(&input[..], TapeSink::new_windowed()).unwrap();
+    let windowed = windowed.into_tape().unwrap();
+    assert_eq!(windowed.words().len(), tape.words().len());
+    let root = windowed.root().unwrap();
+    assert_eq!(root.get("c").unwrap().as_str(), Some("plain"));
+    assert_eq!(root.get("a").unwrap().get_at(2).unwrap().as_str(), Some("x\ty"));
+}
+
+#[test]
+fn pull_events() {
+    let input = br#"{"a": [1.5, "x\ny", true, null], "b": {}}"#;
+    let mut events = Events::new(SliceSource::new(input));
+    let mut collected = vec![];
+    while let Some(event) = events.next() {
+        let event = event.unwrap();
+        if let Event::Number(ref number) = event {
+            assert_eq!(number.to_f64(events.window()), Ok(1.5));
+        }
+        collected.push(event);
+    }
+    let number = match collected[3] {
+        Event::Number(ref number) => number.clone(),
+        ref other => panic!("{:?}", other),
+    };
+    assert_eq!(collected, [
+        Event::StartObject,
+        Event::Key("a".to_owned()),
+        Event::StartArray,
+        Event::Number(number),
+        Event::String("x\ny".to_owned()),
+        Event::Bool(true),
+        Event::Null,
+        Event::EndArray,
+        Event::Key("b".to_owned()),
+        Event::StartObject,
+        Event::EndObject,
+        Event::EndObject,
+    ]);
+
+    // Feeding one byte at a time gives the same events.
+    let mut events = Events::new(ChunkSource::new());
+    let mut chunks = input.iter();
+    let mut fed = vec![];
+    loop {
+        match events.next() {
+            Some(Ok(event)) => fed.push(event),
+            Some(Err(PullError::NeedMoreInput(()))) => match chunks.next() {
+                Some(&byte) => events.source_mut().push(&[byte]),
+                None => events.source_mut().finish(),
+            },
+            Some(Err(err)) => panic!("{:?}", err),
+            None => break,
+        }
+    }
+    assert_eq!(fed, collected);
+
+    // Events before an error are still yielded, and nothing after it.
+    let events: Vec<_> = Events::new(SliceSource::new(b"[true, }")).collect();
+    assert_eq!(events, [
+        Ok(Event::StartArray),
+        Ok(Event::Bool(true)),
+        Err(PullError::Unexpected(7.into(), Unexpected::ObjectClose)),
+    ]);
+}
+
+#[test]
+fn pointer_sink() {
+    use std::io::Read;
+
+    let input = br#"{"data": {"skip": [{"items": 0}], "items": [1, {"id": 2}, "three",
+                    {"id": "x\u0079", "tags": ["a", {}]}]}, "a/b": {"m~n": null}}"#;
+    let pointer = |text: &str| text.parse::<Pointer>().unwrap();
+    let decode = |text: &str| Decoder::new().decode_pointer(input, &pointer(text)).unwrap();
+
+    assert_eq!(decode("/data/items/3/id"), Some(Value::String("xy".to_owned())));
+    assert_eq!(decode("/data/items/1/id"), Some(Value::Number(Number::Integer(2))));
+    assert_eq!(decode("/data/items/3/tags"), Decoder::new().decode_value(br#"["a", {}]"#).ok());
+    assert_eq!(decode("/a~1b/m~0n"), Some(Value::Null));
+    assert_eq!(decode(""), Decoder::new().decode_value(input).ok());
+    for missing in ["/data/items/4", "/data/items/03", "/data/items/-", "/data/id", "/data/items/0/id", "/data/items/2/0"].iter() {
+        assert_eq!(decode(missing), None, "{}", missing);
+    }
+
+    assert_eq!("data".parse::<Pointer>(), Err(PointerError::MissingSlash));
+    assert_eq!("/a~2".parse::<Pointer>(), Err(PointerError::InvalidEscape));
+    assert_eq!(pointer("/a~1b/m~0n").tokens(), ["a/b", "m~n"]);
+    assert_eq!(pointer("/a~1b/m~0n/").to_string(), "/a~1b/m~0n/");
+
+    // Skipped values are only checked for their brackets and quotes.
+    for data in [r#"{"a": [tru, 0], "b": 1}"#, r#"{"a": ["\q"], "b": 1}"#].iter() {
+        let value = Decoder::new().decode_pointer(data.as_bytes(), &pointer("/b")).unwrap();
+        assert_eq!(value, Some(Value::Number(Number::Integer(1))), "{}", data);
+        assert!(Decoder::new().decode_value(data.as_bytes()).is_err(), "{}", data);
+    }
+    match Decoder::new().decode_pointer(br#"{"a": [1}, "b": 1}"#, &pointer("/b")) {
+        Err(Error::Unexpected(Pos(8), Unexpected::ObjectClose)) => (),
+        other => panic!("{:?}", other),
+    }
+
+    // Nothing after the value is read.
+    let endless = std::io::Cursor::new(br#"{"a": [1, [2, 3]], "b": "#.to_vec()).chain(std::io::repeat(b'['));
+    let value = Decoder::new().decode_pointer_reader(endless, &pointer("/a/1/0")).unwrap();
+    assert_eq!(value, Some(Value::Number(Number::Integer(2))));
+
+    // The sink bails once the value is done.
+    let mut sink = PointerSink::new(&pointer("/data/items/1"), ValueSink::new());
+    match Decoder::new().decode_with(input, &mut sink) {
+        Err(Error::Sink(PointerBail::Done)) => (),
+        other => panic!("{:?}", other),
+    }
+    assert!(sink.found());
+    assert_eq!(sink.into_inner().into_value(), Decoder::new().decode_value(br#"{"id": 2}"#).ok());
+
+    // An inner sink bailing on the target's last event still ends it.
+    #[derive(Debug, Default)]
+    struct Bailing {
+        seen: Vec<String>,
+    }
+    impl Bailable for Bailing {
+        type Bail = ();
+    }
+    impl Sink for Bailing {
+        fn push_map(&mut self, _pos: Position) {}
+        fn push_array(&mut self, _pos: Position) {}
+        fn push_number(&mut self, pos: Position, _number: NumberData) -> Result<(), ()> {
+            self.seen.push(format!("number {:?}", pos));
+            Err(())
+        }
+        fn push_bool(&mut self, _pos: Position, _boolean: bool) -> Result<(), ()> { Err(()) }
+        fn push_null(&mut self, _pos: Position) -> Result<(), ()> { Err(()) }
+        fn start_string(&mut self, _pos: StringPosition) {}
+        fn append_string_range(&mut self, _string: Range) {}
+        fn append_string_single(&mut self, _character: u8) {}
+        fn append_string_codepoint(&mut self, _codepoint: char) {}
+        fn finalize_string(&mut self, pos: StringPosition) -> Result<(), ()> {
+            self.seen.push(format!("string {:?}", pos));
+            Err(())
+        }
+        fn finalize_array(&mut self, pos: Position) -> Result<(), ()> {
+            self.seen.push(format!("array {:?}", pos));
+            Err(())
+        }
+        fn finalize_map(&mut self, _pos: Position) -> Result<(), ()> { Err(()) }
+        fn pop_into_map(&mut self) {}
+        fn pop_into_array(&mut self) {}
+    }
+
+    for (target, expected) in [("/a", vec!["number ArrayValue", "array Root"]), ("/b", vec!["string Root"]),
+                               ("/c", vec!["number Root"])].iter() {
+        let mut ss = SourceSink {
+            source: SliceSource::new(br#"{"a": [1], "b": "x", "c": 2, "d": [3, "y"]}"#),
+            sink: PointerSink::new(&pointer(target), Bailing::default()),
+        };
+        let mut parser = Parser::new();
+        let mut bails = 0;
+        loop {
+            match parser.run(&mut ss) {
+                Ok(()) => break,
+                Err(ParseError::SourceBail(BailVariant::Sink(PointerBail::Inner(())))) => bails += 1,
+                Err(err) => panic!("{}: {:?}", target, err),
+            }
+        }
+        assert_eq!(bails, expected.len(), "{}", target);
+        assert!(ss.sink.found(), "{}", target);
+        assert_eq!(ss.sink.inner().seen, *expected, "{}", target);
+    }
+}
+
+#[test]
+fn query_sink() {
+    let input = br#"{"store": {"name": "corner", "items": [
+                        {"id": 1, "name": "apple", "price": 0.5, "tags": ["fruit"]},
+                        {"id": 2, "name": "pear", "price": 2, "stock": {"name": "shelf"}},
+                        {"id": 3, "name": "plum", "price": 1.25},
+                        {"id": 4, "name": "fig", "price": 3, "tags": []}]},
+                    "owner": {"name": "ann"}}"#;
+    let document = Decoder::new().decode_value(input).unwrap();
+    let stream = |query: &str| Decoder::new().decode_query(input, &query.parse().unwrap()).unwrap();
+    let strings = |values: Vec<Value>| -> Vec<String> {
+        values.iter().map(|value| value.as_str().unwrap().to_owned()).collect()
+    };
+    let numbers = |values: Vec<Value>| -> Vec<i64> {
+        values.iter().map(|value| value.as_i64().unwrap()).collect()
+    };
+
+    assert_eq!(strings(stream("$.store.name")), ["corner"]);
+    assert_eq!(strings(stream("$['owner']['name']")), ["ann"]);
+    assert_eq!(numbers(stream("$.store.items[*].id")), [1, 2, 3, 4]);
+    assert_eq!(numbers(stream("$.store.items[1].id")), [2]);
+    assert_eq!(numbers(stream("$.store.items[1:].id")), [2, 3, 4]);
+    assert_eq!(numbers(stream("$.store.items[0:4:2].id")), [1, 3]);
+    assert_eq!(numbers(stream("$.store.items[?(@.price >= 2)].id")), [2, 4]);
+    assert_eq!(numbers(stream("$.store.items[?(@.name == 'plum')].id")), [3]);
+    assert_eq!(numbers(stream("$.store.items[?(@.tags)].id")), [1, 4]);
+    assert_eq!(numbers(stream("$.store.items[?(@.stock.name != \"shelf\")].id")), [1, 3, 4]);
+    assert_eq!(strings(stream("$..items[?(@.tags[0] == 'fruit')].name")), ["apple"]);
+    assert_eq!(stream("$").first(), Some(&document));
+    assert_eq!(stream("$.missing"), []);
+
+    assert_eq!(strings(stream("$..name")), ["corner", "apple", "pear", "shelf", "plum", "fig", "ann"]);
+
+    // A match in a match comes after it, in document order.
+    let nested = br#"{"a": {"a": {"a": 1}}}"#;
+    let values = Decoder::new().decode_query(nested, &"$..a".parse().unwrap()).unwrap();
+    assert_eq!(values.len(), 3);
+    assert_eq!(values[0]["a"]["a"].as_i64(), Some(1));
+    assert_eq!(values[2].as_i64(), Some(1));
+
+    // Streaming gives the same matches in the same order as `select`.
+    let documents: [&[u8]; 3] = [
+        input,
+        br#"{"a": {"x": {"a": {"b": 1}}, "b": 2}}"#,
+        br#"[{"x": [1, {"x": 2}]}, [{"x": 3, "y": [{"x": 4}]}]]"#,
+    ];
+    let queries = [
+        "$..*", "$..name", "$..a.b", "$..x", "$..[0]", "$..[?(@.x)]", "$..[?(@.x)]..x", "$..*[*]",
+    ];
+    for &data in documents.iter() {
+        let document = Decoder::new().decode_value(data).unwrap();
+        for &query in queries.iter() {
+            let query: Query = query.parse().unwrap();
+            let selected: Vec<Value> = query.select(&document).into_iter().cloned().collect();
+            assert_eq!(Decoder::new().decode_query(data, &query).unwrap(), selected, "{:?}", query);
+        }
+    }
+    let query: Query = "$..a.b".parse().unwrap();
+    let values = Decoder::new().decode_query(documents[1], &query).unwrap();
+    assert_eq!(values.iter().map(|value| value.as_i64().unwrap()).collect::<Vec<_>>(), [1, 2]);
+
+    assert_eq!("store".parse::<Query>(), Err(QueryError::Syntax(0)));
+    assert_eq!("$.a[".parse::<Query>(), Err(QueryError::Syntax(4)));
+    assert_eq!("$[-1]".parse::<Query>(), Err(QueryError::Unsupported(2)));
+    assert_eq!("$[::0]".parse::<Query>(), Err(QueryError::Syntax(2)));
+
+    // The callback can stop parsing.
+    let mut first = None;
+    let sink = QuerySink::new(&"$..id".parse().unwrap(), |value: Value| {
+        first = Some(value);
+        Err("enough")
+    });
+    match Decoder::new().decode_with(input, sink) {
+        Err(Error::Sink(QueryBail::Callback("enough"))) => (),
+        other => panic!("{:?}", other.err()),
+    }
+    assert_eq!(first.and_then(|value| value.as_i64()), Some(1));
+}
+
+#[test]
+fn path_tracking() {
+    // Records the path of each scalar and string value.
+    #[derive(Debug, Default)]
+    struct Paths {
+        current: String,
+        seen: Vec<String>,
+    }
+    impl Bailable for Paths {
+        type Bail = ();
+    }
+    impl Sink for Paths {
+        fn push_map(&mut self, _pos: Position) {}
+        fn push_array(&mut self, _pos: Position) {}
+        fn push_number(&mut self, _pos: Position, _number: NumberData) -> Result<(), ()> {
+            self.seen.push(self.current.clone());
+            Ok(())
+        }
+        fn push_bool(&mut self, _pos: Position, _boolean: bool) -> Result<(), ()> {
+            // Bails, to check that parsing resumes at the right path.
+            self.seen.push(self.current.clone());
+            Err(())
+        }
+        fn push_null(&mut self, _pos: Position) -> Result<(), ()> {
+            self.seen.push(self.current.clone());
+            Ok(())
+        }
+        fn start_string(&mut self, _pos: StringPosition) {}
+        fn append_string_range(&mut self, _string: Range) {}
+        fn append_string_single(&mut self, _character: u8) {}
+        fn append_string_codepoint(&mut self, _codepoint: char) {}
+        fn finalize_string(&mut self, pos: StringPosition) -> Result<(), ()> {
+            if pos != StringPosition::MapKey {
+                self.seen.push(self.current.clone());
+            }
+            Ok(())
+        }
+        fn finalize_array(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn finalize_map(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn pop_into_map(&mut self) {}
+        fn pop_into_array(&mut self) {}
+    }
+    impl PathSink for Paths {
+        fn at_path(&mut self, path: &Path) {
+            self.current = path.to_string();
+        }
+    }
+
+    let input = br#"{"a": [1, {"b": null, "c d": "x"}, [true, 2]], "\u00e9": {}, "e": 3}"#;
+    let mut tracking = PathTracking::new(Paths::default());
+    let mut parser = Parser::new();
+    let mut ss = SourceSink {
+        source: SliceSource::new(input),
+        sink: &mut tracking,
+    };
+    let mut bails = 0;
+    loop {
+        match parser.run(&mut ss) {
+            Ok(()) => break,
+            Err(ParseError::SourceBail(BailVariant::Sink(()))) => {
+                assert_eq!(ss.sink.path().to_string(), "$.a[2][0]");
+                bails += 1;
+            },
+            Err(err) => panic!("{:?}", err),
+        }
+    }
+    assert_eq!(bails, 1);
+    assert_eq!(tracking.inner().seen, [
+        "$.a[0]", "$.a[1].b", "$.a[1]['c d']", "$.a[2][0]", "$.a[2][1]", "$.e",
+    ]);
+    assert!(tracking.path().is_root());
+
+    // The path is attached to errors.
+    let mut tracking = PathTracking::new(ValueSink::new());
+    let err = Decoder::new().decode_with(br#"{"a": [1, {"b": 1e400}]}"#, &mut tracking).unwrap_err();
+    let err = tracking.attach(err);
+    assert_eq!(err.path.to_pointer().to_string(), "/a/1/b");
+    assert_eq!(err.to_string(), "aborted by sink: Number(OutOfRange) at $.a[1].b");
+
+    let mut tracking = PathTracking::new(ValueSink::new());
+    let err = Decoder::new().decode_with(br#"{"a": {"b": [1, 2 3]}}"#, &mut tracking).unwrap_err();
+    assert_eq!(tracking.attach(err).path.to_string(), "$.a.b");
+
+    // Errors in a member value have the key in the path.
+    let mut tracking = PathTracking::new(ValueSink::new());
+    let err = Decoder::new().decode_with(br#"{"a": [1, {"b": x}]}"#, &mut tracking).unwrap_err();
+    assert!(matches!(err, Error::Unexpected(Pos(16), Unexpected::Character)), "{:?}", err);
+    assert_eq!(tracking.attach(err).path.to_string(), "$.a[1].b");
+}
+
+// Events of `input` fed one byte at a time, skipping the values of the
+// given keys.
+fn skipping_events(input: &[u8], keys: &[&str]) -> Vec<Result<Event, PullError<()>>> {
+    let mut events = Events::new(ChunkSource::new());
+    let mut chunks = input.iter();
+    let mut collected = vec![];
+    loop {
+        match events.next() {
+            Some(Err(PullError::NeedMoreInput(()))) => match chunks.next() {
+                Some(&byte) => events.source_mut().push(&[byte]),
+                None => events.source_mut().finish(),
+            },
+            Some(event) => {
+                if let Ok(Event::Key(ref key)) = event {
+                    if keys.contains(&&key[..]) {
+                        assert!(events.skip_value());
+                    }
+                }
+                collected.push(event);
+            }
+            None => return collected,
+        }
+    }
+}
+
+#[test]
+fn skip_value() {
+    let input = br#"{"a": {"x": [1, "]}\""], "y": {}}, "b": true, "c": "s\"}", "d": 3}"#;
+    assert_eq!(skipping_events(input, &["a", "c", "d"]), [
+        Ok(Event::StartObject),
+        Ok(Event::Key("a".to_owned())),
+        Ok(Event::Key("b".to_owned())),
+        Ok(Event::Bool(true)),
+        Ok(Event::Key("c".to_owned())),
+        Ok(Event::Key("d".to_owned())),
+        Ok(Event::EndObject),
+    ]);
+
+    // A value can not be skipped once it has started.
+    let mut events = Events::new(SliceSource::new(b"[[1], 2]"));
+    assert_eq!(events.next(), Some(Ok(Event::StartArray)));
+    assert!(!events.skip_value());
+
+    // Brackets in skipped values still have to match.
+    let errors = [
+        (&br#"{"a": [1, 2}, "b": 3}"#[..], 11, Unexpected::ObjectClose),
+        (&br#"{"a": {"x": 1]}"#[..], 13, Unexpected::ArrayClose),
+        (&br#"{"a": ["x]"#[..], 10, Unexpected::Eof),
+    ];
+    for &(input, pos, reason) in errors.iter() {
+        let events = skipping_events(input, &["a"]);
+        assert_eq!(events.last(), Some(&Err(PullError::Unexpected(pos.into(), reason))));
+    }
+
+    // A sink skipping all nested values gets no calls for them, except for
+    // the scalars it is not asked about.
+    #[derive(Debug, Default)]
+    struct SkipNested {
+        calls: usize,
+        pops: usize,
+    }
+    impl Bailable for SkipNested {
+        type Bail = ();
+    }
+    impl Sink for SkipNested {
+        fn push_map(&mut self, _pos: Position) { self.calls += 1 }
+        fn push_array(&mut self, _pos: Position) { self.calls += 1 }
+        fn push_number(&mut self, _pos: Position, _number: NumberData) -> Result<(), ()> { self.calls += 1; Ok(()) }
+        fn push_bool(&mut self, _pos: Position, _boolean: bool) -> Result<(), ()> { self.calls += 1; Ok(()) }
+        fn push_null(&mut self, _pos: Position) -> Result<(), ()> { self.calls += 1; Ok(()) }
+        fn start_string(&mut self, _pos: StringPosition) { self.calls += 1 }
+        fn append_string_range(&mut self, _string: Range) {}
+        fn append_string_single(&mut self, _character: u8) {}
+        fn append_string_codepoint(&mut self, _codepoint: char) {}
+        fn finalize_string(&mut self, _pos: StringPosition) -> Result<(), ()> { Ok(()) }
+        fn finalize_array(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn finalize_map(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn pop_into_map(&mut self) { self.pops += 1 }
+        fn pop_into_array(&mut self) { self.pops += 1 }
+        fn skip_value(&mut self, pos: Position) -> bool {
+            pos != Position::Root
+        }
+    }
+    let mut sink = SkipNested::default();
+    let mut parser = Parser::new();
+    {
+        let mut ss = SourceSink {
+            source: SliceSource::new(br#"[[1, [2]], {"a": 1}, "x", 3]"#),
+            sink: &mut sink,
+        };
+        assert!(parser.run(&mut ss).is_ok());
+    }
+    // The outer array and the number.
+    assert_eq!(sink.calls, 2);
+    assert_eq!(sink.pops, 1);
+}
+
+// Reads one byte at a time, so that parsing is resumed everywhere.
+struct Bytewise<'a>(&'a [u8]);
+
+impl<'a> std::io::Read for Bytewise<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        (&self.0[..self.0.len().min(1)]).read(buf).inspect(|&read| self.0 = &self.0[read..])
+    }
+}
+
+// Decodes each document of `input`, checking that reading it byte by byte
+// gives the same results.
+fn documents(input: &[u8], documents: Documents) -> Vec<Result<Value, Error<ValueError>>> {
+    let decoder = Decoder::new();
+    let from_slice = decoder.decode_documents(input, documents);
+    let from_reader = decoder.decode_documents_reader(Bytewise(input), documents);
+    assert_eq!(format!("{:?}", from_slice), format!("{:?}", from_reader));
+    from_slice
+}
+
+fn document_values(input: &[u8], mode: Documents) -> Vec<Value> {
+    documents(input, mode).into_iter().map(|document| document.unwrap()).collect()
+}
+
+fn document_errors(input: &[u8], mode: Documents) -> Vec<Option<(usize, Unexpected)>> {
+    documents(input, mode).into_iter().map(|document| match document {
+        Ok(_) => None,
+        Err(Error::Unexpected(pos, reason)) => Some((pos.0, reason)),
+        Err(err) => panic!("{:?}", err),
+    }).collect()
+}
+
+#[test]
+fn json_lines() {
+    let decoder = Decoder::new();
+    let lines = |input: &[u8]| -> Vec<Result<Value, Error<ValueError>>> {
+        let results = documents(input, Documents::Lines);
+        assert_eq!(format!("{:?}", results), format!("{:?}", decoder.decode_lines(input)));
+        results
+    };
+    let values = |input: &[u8]| -> Vec<Value> {
+        lines(input).into_iter().map(|line| line.unwrap()).collect()
+    };
+
+    assert_eq!(values(b""), vec![]);
+    assert_eq!(values(b"{\"a\": 1}\n[2]\n[\"three\"]\n"), vec![
+        decoder.decode_value(b"{\"a\": 1}").unwrap(),
+        decoder.decode_value(b"[2]").unwrap(),
+        decoder.decode_value(b"[\"three\"]").unwrap(),
+    ]);
+    // Blank lines, CRLF, and no newline after the last line.
+    assert_eq!(values(b"\n  \r\n[true] \r\n\n\n{}"), vec![
+        decoder.decode_value(b"[true]").unwrap(),
+        decoder.decode_value(b"{}").unwrap(),
+    ]);
+
+    // A bad line does not stop the lines after it.
+    let results = lines(b"[1]\n[1,\n{}\n[] 2\n[\"a\nb\"]\n[null] x\n[]");
+    assert_eq!(results.len(), 8);
+    assert!(results[0].is_ok());
+    match results[1] {
+        Err(Error::Unexpected(Pos(7), Unexpected::LineEnd)) => (),
+        ref other => panic!("{:?}", other),
+    }
+    assert!(results[2].is_ok());
+    match results[3] {
+        Err(Error::Unexpected(Pos(14), Unexpected::Character)) => (),
+        ref other => panic!("{:?}", other),
+    }
+    // Strings can not hold a raw newline, so the rest of the string is the
+    // next line.
+    match results[4] {
+        Err(Error::Unexpected(Pos(19), Unexpected::LineEnd)) => (),
+        ref other => panic!("{:?}", other),
+    }
+    match results[5] {
+        Err(Error::Unexpected(Pos(20), _)) => (),
+        ref other => panic!("{:?}", other),
+    }
+    match results[6] {
+        Err(Error::Unexpected(Pos(31), Unexpected::Character)) => (),
+        ref other => panic!("{:?}", other),
+    }
+    assert_eq!(results[7].as_ref().ok(), Some(&Value::Array(vec![])));
+
+    // Root values can be scalars, since each one ends with its line.
+    assert_eq!(values(b"1\n\"two\"\n-3.5 \nnull"), vec![
+        Value::Number(Number::Integer(1)),
+        Value::String("two".to_string()),
+        Value::Number(Number::Float(-3.5)),
+        Value::Null,
+    ]);
+    assert_eq!(document_errors(b"1 2\n-\ntru\n\"a", Documents::Lines), vec![
+        Some((2, Unexpected::Character)),
+        Some((5, Unexpected::LineEnd)),
+        Some((9, Unexpected::LineEnd)),
+        Some((12, Unexpected::Eof)),
+    ]);
+
+    // A value the sink rejects only fails its own line.
+    let results = lines(b"[1, 1e309, {}]\n[]");
+    match results[0] {
+        Err(Error::Sink(ValueError::Number(NumberError::OutOfRange))) => (),
+        ref other => panic!("{:?}", other),
+    }
+    assert!(results[1].is_ok());
+
+    // The document size limit applies to each line.
+    let limited = Decoder::with_options(Options {
+        limits: Limits { max_document_size: Some(4), ..Limits::default() },
+        ..Options::default()
+    });
+    let results = limited.decode_lines(b"[1]\n[1,2,3]\n[2]");
+    assert!(results[0].is_ok());
+    match results[1] {
+        Err(Error::Unexpected(Pos(8), Unexpected::DocumentLimit)) => (),
+        ref other => panic!("{:?}", other),
+    }
+    assert!(results[2].is_ok());
+
+    // Records the range of each document, bailing at each boundary.
+    #[derive(Debug, Default)]
+    struct Boundaries {
+        started: Vec<usize>,
+        ended: Vec<(Range, Option<Unexpected>)>,
+    }
+    impl Bailable for Boundaries {
+        type Bail = ();
+    }
+    impl Sink for Boundaries {
+        fn push_map(&mut self, _pos: Position) {}
+        fn push_array(&mut self, _pos: Position) {}
+        fn push_number(&mut self, _pos: Position, _number: NumberData) -> Result<(), ()> { Ok(()) }
+        fn push_bool(&mut self, _pos: Position, _boolean: bool) -> Result<(), ()> { Ok(()) }
+        fn push_null(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn start_string(&mut self, _pos: StringPosition) {}
+        fn append_string_range(&mut self, _string: Range) {}
+        fn append_string_single(&mut self, _character: u8) {}
+        fn append_string_codepoint(&mut self, _codepoint: char) {}
+        fn finalize_string(&mut self, _pos: StringPosition) -> Result<(), ()> { Ok(()) }
+        fn finalize_array(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn finalize_map(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn pop_into_map(&mut self) {}
+        fn pop_into_array(&mut self) {}
+        fn start_document(&mut self, start: Pos) {
+            self.started.push(start.0);
+        }
+        fn end_document(&mut self, document: Range) -> Result<(), ()> {
+            self.ended.push((document, None));
+            Err(())
+        }
+        fn document_error(&mut self, document: Range, _pos: Pos, reason: Unexpected) -> Result<(), ()> {
+            self.ended.push((document, Some(reason)));
+            Err(())
+        }
+    }
+
+    let input = b" [1] \n\n[] y\n {}";
+    let mut ss = SourceSink {
+        source: SliceSource::new(input),
+        sink: Boundaries::default(),
+    };
+    let mut parser = Parser::with_options(Options {
+        documents: Documents::Lines,
+        ..Options::default()
+    });
+    let mut bails = 0;
+    loop {
+        match parser.run(&mut ss) {
+            Ok(()) => break,
+            Err(ParseError::SourceBail(BailVariant::Sink(()))) => bails += 1,
+            Err(err) => panic!("{:?}", err),
+        }
+    }
+    assert_eq!(bails, 3);
+    assert_eq!(ss.sink.started, vec![1, 7, 13]);
+    assert_eq!(ss.sink.ended, vec![
+        (Range { start: 1, end: 4 }, None),
+        (Range { start: 7, end: 11 }, Some(Unexpected::Character)),
+        (Range { start: 13, end: 15 }, None),
+    ]);
+}
+
+#[test]
+fn single_document_methods() {
+    // The methods returning one value ignore `Options::documents`.
+    let input = b"{\"a\": [1, \"b\"]}\n";
+    for &mode in [Documents::Lines, Documents::Sequence, Documents::Concatenated].iter() {
+        let decoder = Decoder::with_options(Options { documents: mode, ..Options::default() });
+        let expected = Decoder::new().decode_value(input).unwrap();
+        assert_eq!(decoder.decode_value(input).unwrap(), expected);
+        assert_eq!(decoder.decode_value_reader(&input[..]).unwrap(), expected);
+        assert_eq!(decoder.decode_borrowed(input).unwrap(), Decoder::new().decode_borrowed(input).unwrap());
+        let tape = decoder.decode_tape(input).unwrap();
+        assert_eq!(tape.words(), Decoder::new().decode_tape(input).unwrap().words());
+        assert_eq!(tape.root().unwrap().get("a").unwrap().get_at(1).unwrap().as_str(), Some("b"));
+        assert!(decoder.decode_slice(input).is_ok());
+        assert!(decoder.decode_reader(&input[..]).is_ok());
+
+        let eof = |result: Result<(), Error<ValueError>>| match result {
+            Err(Error::Unexpected(Pos(0), Unexpected::Eof)) => (),
+            other => panic!("{:?}", other),
+        };
+        eof(decoder.decode_value(b"").map(|_| ()));
+        eof(decoder.decode_value_reader(&b""[..]).map(|_| ()));
+        eof(decoder.decode_borrowed(b"").map(|_| ()));
+        eof(decoder.decode_tape(b"").map(|_| ()));
+
+        // Root scalars are only allowed with several documents.
+        let scalar = |result: Result<(), Error<ValueError>>| match result {
+            Err(Error::Unexpected(Pos(0), Unexpected::Number)) => (),
+            other => panic!("{:?}", other),
+        };
+        scalar(decoder.decode_value(b"1\n2\n").map(|_| ()));
+        scalar(decoder.decode_value_reader(&b"1\n2\n"[..]).map(|_| ()));
+        scalar(decoder.decode_borrowed(b"1\n2\n").map(|_| ()));
+        scalar(decoder.decode_tape(b"1\n2\n").map(|_| ()));
+    }
+}
+
+#[test]
+fn json_text_sequences() {
+    let input = b"x\n\x1e{\"a\":1}\n\x1e[1,\x1e2\n\x1e\"x\"\n\x1e\x1e7\n\x1e7\x1etrue\n";
+    let results = documents(input, Documents::Sequence);
+    assert_eq!(results.len(), 8);
+    let values: Vec<_> = results.iter().map(|result| result.as_ref().ok()).collect();
+    assert_eq!(values[1], Some(&Decoder::new().decode_value(b"{\"a\":1}").unwrap()));
+    assert_eq!(values[3], Some(&Value::Number(Number::Integer(2))));
+    assert_eq!(values[4], Some(&Value::String("x".to_string())));
+    assert_eq!(values[5], Some(&Value::Number(Number::Integer(7))));
+    assert_eq!(values[7], Some(&Value::Bool(true)));
+    assert_eq!(document_errors(input, Documents::Sequence), vec![
+        // A document without a record separator before it.
+        Some((0, Unexpected::Character)),
+        None,
+        // A record cut off by the next one.
+        Some((15, Unexpected::Truncated)),
+        None,
+        None,
+        None,
+        // A root number not followed by whitespace may have been cut off.
+        Some((29, Unexpected::Truncated)),
+        None,
+    ]);
+
+    // A record holds a single document.
+    assert_eq!(document_errors(b"\x1etrue\nnull\n", Documents::Sequence), vec![Some((6, Unexpected::Character))]);
+    assert_eq!(document_errors(b"\x1e[1", Documents::Sequence), vec![Some((3, Unexpected::Truncated))]);
+    assert_eq!(document_errors(b"\x1e\"a", Documents::Sequence), vec![Some((3, Unexpected::Truncated))]);
+    assert_eq!(document_errors(b"\x1e12", Documents::Sequence), vec![Some((3, Unexpected::Truncated))]);
+    assert_eq!(document_errors(b"\x1enul\x1e\"a\x1e[\"b\x1e", Documents::Sequence), vec![
+        Some((4, Unexpected::Truncated)),
+        Some((7, Unexpected::Truncated)),
+        Some((11, Unexpected::Truncated)),
+    ]);
+    assert_eq!(document_errors(b"\x1e1 2\n\x1e{} x\n", Documents::Sequence), vec![
+        Some((3, Unexpected::Character)),
+        Some((9, Unexpected::Character)),
+    ]);
+    assert_eq!(document_values(b"", Documents::Sequence), vec![]);
+    assert_eq!(document_values(b"\x1e\n\x1e  \x1e", Documents::Sequence), vec![]);
+    assert_eq!(document_values(b"\x1e 12.5e1 \n", Documents::Sequence), vec![Value::Number(Number::Float(125.0))]);
+}
+
+#[test]
+fn concatenated_json() {
+    let input = b"{}{}[1]\"x\" 1 2\n-3 true[]null";
+    let json = document_values(input, Documents::Concatenated).iter().map(|value| format!("{:?}", value)).collect::<Vec<_>>();
+    assert_eq!(json.len(), 10);
+    assert_eq!(document_values(b"12\"a\"1.5e3[]", Documents::Concatenated), vec![
+        Value::Number(Number::Integer(12)),
+        Value::String("a".to_string()),
+        Value::Number(Number::Float(1500.0)),
+        Value::Array(vec![]),
+    ]);
+    assert_eq!(document_values(b"  \n ", Documents::Concatenated), vec![]);
+
+    // There is no telling where the next document starts after an error.
+    assert_eq!(document_errors(b"[1]]{}", Documents::Concatenated), vec![None, Some((3, Unexpected::ArrayClose))]);
+    assert_eq!(document_errors(b"1,2", Documents::Concatenated), vec![None, Some((1, Unexpected::Comma))]);
+    assert_eq!(document_errors(b"{}[", Documents::Concatenated), vec![None, Some((3, Unexpected::Eof))]);
+
+    // Root scalars are still not allowed in a single document.
+    match Decoder::new().decode_value(b"1") {
+        Err(Error::Unexpected(_, Unexpected::Number)) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+// Decodes JSON5, checking that reading it byte by byte gives the same
+// result.
+fn json5(input: &[u8], strict: bool) -> Result<Value, Error<ValueError>> {
+    let decoder = Decoder::with_options(Options { dialect: Dialect::Json5, strict, ..Options::default() });
+    let from_slice = decoder.decode_value(input);
+    let from_reader = decoder.decode_value_reader(Bytewise(input));
+    match (&from_slice, &from_reader) {
+        // Objects have no stable debug output, NaN is not equal to itself.
+        (Ok(a), Ok(b)) if a == b => (),
+        (a, b) => assert_eq!(format!("{:?}", a), format!("{:?}", b)),
+    }
+    from_slice
+}
+
+fn json5_error(input: &[u8]) -> (usize, Unexpected) {
+    match json5(input, false) {
+        Err(Error::Unexpected(pos, reason)) => (pos.0, reason),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn json5_dialect() {
+    let input = r#"{
+        // Comments are JSON5 too.
+        unquoted: 'and you can quote me on that', /* [ */
+        singleQuotes: 'I can use "double quotes" here',
+        lineBreaks: "Look, Mom! \
+No \\n's!",
+        hexadecimal: 0xdecaf,
+        leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+        positiveSign: +1,
+        trailingComma: 'in objects', andIn: ['arrays',],
+        "backwardsCompatible": "with JSON",
+        $_ü1: [-0x10, 0XfF, +.5, -5.e1, '\x41\0\v\'\q\ü', "\'\r\n"],
+    }"#.as_bytes();
+    let expected = Decoder::new().decode_value(br#"{
+        "unquoted": "and you can quote me on that",
+        "singleQuotes": "I can use \"double quotes\" here",
+        "lineBreaks": "Look, Mom! No \\n's!",
+        "hexadecimal": 912559,
+        "leadingDecimalPoint": 0.8675309, "andTrailing": 8675309.0,
+        "positiveSign": 1,
+        "trailingComma": "in objects", "andIn": ["arrays"],
+        "backwardsCompatible": "with JSON",
+        "$_\u00fc1": [-16, 255, 0.5, -50.0, "A\u0000\u000b'q\u00fc", "'\r\n"]
+    }"#).unwrap();
+    assert_eq!(json5(input, false).unwrap(), expected);
+    assert_eq!(json5(input, true).unwrap(), expected);
+
+    let special = json5(b"[Infinity, -Infinity, NaN, 0x1F]", false).unwrap();
+    let special: Vec<_> = special.as_array().unwrap().iter().map(|value| value.as_f64().unwrap()).collect();
+    assert_eq!(special[..2], [f64::INFINITY, f64::NEG_INFINITY]);
+    assert!(special[2].is_nan());
+    assert_eq!(special[3], 31.0);
+    assert_eq!(json5(b"['a\\\r\nb\\\rc']", false).unwrap(), Value::Array(vec![Value::String("abc".to_string())]));
+
+    // Errors.
+    assert_eq!(json5_error(b"{a b: 1}"), (3, Unexpected::Character));
+    assert_eq!(json5_error(b"[a]"), (1, Unexpected::Character));
+    assert_eq!(json5_error(b"{1: 1}"), (1, Unexpected::Number));
+    assert_eq!(json5_error(b"[0x]"), (3, Unexpected::Character));
+    assert_eq!(json5_error(b"[1x5]"), (2, Unexpected::Character));
+    assert_eq!(json5_error(b"[0x5.5]"), (4, Unexpected::Dot));
+    assert_eq!(json5_error(b"[.]"), (2, Unexpected::EmptyFraction));
+    assert_eq!(json5_error(b"[1Infinity]"), (2, Unexpected::Number));
+    assert_eq!(json5_error(b"['\\1']"), (3, Unexpected::InvalidEscape));
+    assert_eq!(json5_error(b"['\\01']"), (4, Unexpected::InvalidEscape));
+    assert_eq!(json5_error(b"['a\\\"]"), (6, Unexpected::Eof));
+    assert_eq!(json5_error(b"[1,,]"), (3, Unexpected::Comma));
+    match json5(b"[012]", true) {
+        Err(Error::Unexpected(Pos(1), Unexpected::LeadingZero)) => (),
+        other => panic!("{:?}", other),
+    }
+
+    // Hexadecimal numbers do not depend on the source's window.
+    let options = Options { dialect: Dialect::Json5, ..Options::default() };
+    let input = b"[0x1F, -0X10, 0, 10]";
+    let mut ss = SourceSink {
+        source: Bytes { bytes: input, pos: 0 },
+        sink: BorrowedValueSink::new(input),
+    };
+    Parser::with_options(options).run(&mut ss).unwrap();
+    assert_eq!(ss.sink.into_value(), Decoder::with_options(options).decode_borrowed(input).ok());
+
+    // None of it is JSON.
+    for input in ["{a: 1}", "['a']", "[0x1]", "[.5]", "[5.]", "[Infinity]", "[\"\\x41\"]"].iter() {
+        assert!(Decoder::new().decode_value(input.as_bytes()).is_err(), "{}", input);
+    }
+
+    // Sinks can tell how strings and numbers are written.
+    #[derive(Debug, Default)]
+    struct Kinds {
+        strings: Vec<StringKind>,
+        numbers: Vec<NumberKind>,
+    }
+    impl Bailable for Kinds {
+        type Bail = ();
+    }
+    impl Sink for Kinds {
+        fn push_map(&mut self, _pos: Position) {}
+        fn push_array(&mut self, _pos: Position) {}
+        fn push_number(&mut self, _pos: Position, number: NumberData) -> Result<(), ()> {
+            self.numbers.push(number.kind);
+            Ok(())
+        }
+        fn push_bool(&mut self, _pos: Position, _boolean: bool) -> Result<(), ()> { Ok(()) }
+        fn push_null(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn start_string(&mut self, _pos: StringPosition) {
+            panic!();
+        }
+        fn start_string_kind(&mut self, _pos: StringPosition, kind: StringKind) {
+            self.strings.push(kind);
+        }
+        fn append_string_range(&mut self, _string: Range) {}
+        fn append_string_single(&mut self, _character: u8) {}
+        fn append_string_codepoint(&mut self, _codepoint: char) {}
+        fn finalize_string(&mut self, _pos: StringPosition) -> Result<(), ()> { Ok(()) }
+        fn finalize_array(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn finalize_map(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn pop_into_map(&mut self) {}
+        fn pop_into_array(&mut self) {}
+    }
+
+    let decoder = Decoder::with_options(Options { dialect: Dialect::Json5, ..Options::default() });
+    let kinds = decoder.decode_with(b"{a: 'b', \"c\": [1, 0x1, -Infinity, NaN, .1]}", Kinds::default()).unwrap();
+    assert_eq!(kinds.strings, vec![StringKind::Identifier, StringKind::SingleQuoted, StringKind::DoubleQuoted]);
+    assert_eq!(kinds.numbers, vec![
+        NumberKind::Decimal,
+        NumberKind::Hexadecimal,
+        NumberKind::Infinity,
+        NumberKind::NaN,
+        NumberKind::Decimal,
+    ]);
+}
+
+#[test]
+fn comments() {
+    let decoder = Decoder::with_options(Options { comments: true, ..Options::default() });
+    let input = b"// Settings\n{\"a\": /* one */ 1, // the first\n \"b\": [2/**/, /*/ 3 **/ 3]} /* end */";
+    let expected = Decoder::new().decode_value(b"{\"a\": 1, \"b\": [2, 3]}").unwrap();
+    assert_eq!(decoder.decode_value(input).unwrap(), expected);
+    assert_eq!(decoder.decode_value_reader(Bytewise(input)).unwrap(), expected);
+    assert!(Decoder::new().decode_value(input).is_err());
+
+    // Records the text of each comment.
+    #[derive(Debug, Default)]
+    struct Comments(Vec<String>);
+    impl Bailable for Comments {
+        type Bail = ();
+    }
+    impl Sink for Comments {
+        fn push_map(&mut self, _pos: Position) {}
+        fn push_array(&mut self, _pos: Position) {}
+        fn push_number(&mut self, _pos: Position, _number: NumberData) -> Result<(), ()> { Ok(()) }
+        fn push_bool(&mut self, _pos: Position, _boolean: bool) -> Result<(), ()> { Ok(()) }
+        fn push_null(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn start_string(&mut self, _pos: StringPosition) {}
+        fn append_string_range(&mut self, _string: Range) {}
+        fn append_string_single(&mut self, _character: u8) {}
+        fn append_string_codepoint(&mut self, _codepoint: char) {}
+        fn finalize_string(&mut self, _pos: StringPosition) -> Result<(), ()> { Ok(()) }
+        fn finalize_array(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn finalize_map(&mut self, _pos: Position) -> Result<(), ()> { Ok(()) }
+        fn pop_into_map(&mut self) {}
+        fn pop_into_array(&mut self) {}
+        fn comment_window(&mut self, comment: Range, window: Window) {
+            self.0.push(String::from_utf8(window.get(comment).unwrap().to_vec()).unwrap());
+        }
+    }
+
+    // Comments after the root value are not read in a single document.
+    let expected = vec!["// Settings", "/* one */", "// the first", "/**/", "/*/ 3 **/"];
+    assert_eq!(decoder.decode_with(input, Comments::default()).unwrap().0, expected);
+    assert_eq!(decoder.decode_reader_with(Bytewise(input), Comments::default()).unwrap().0, expected);
+
+    let error = |input: &[u8]| match decoder.decode_value(input) {
+        Err(Error::Unexpected(pos, reason)) => (pos.0, reason),
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(error(b"[1 / 2]"), (3, Unexpected::Character));
+    assert_eq!(error(b"[1 /* 2]"), (8, Unexpected::Eof));
+    assert_eq!(error(b"[1 // 2]"), (8, Unexpected::Eof));
+
+    // Comments in skipped values are matched, so brackets in them do not
+    // count.
+    let pointer = "/b".parse::<Pointer>().unwrap();
+    assert_eq!(decoder.decode_pointer(b"{\"a\": [/* ] */ 1], \"b\": 2}", &pointer).unwrap(), Some(Value::Number(Number::Integer(2))));
+    assert_eq!(decoder.decode_pointer(b"{\"a\": [// ]\n], \"b\": 2}", &pointer).unwrap(), Some(Value::Number(Number::Integer(2))));
+
+    // With several documents, comments can be on lines of their own, and
+    // after each document.
+    let lines = Decoder::with_options(Options { comments: true, documents: Documents::Lines, ..Options::default() });
+    let results = lines.decode_lines(b"// first\n1 // one\n/* two */ 2/**/\n/* three\n3\n[4, /* ] */ 4]\n/ 5\n");
+    let results: Vec<_> = results.iter().map(|result| match *result {
+        Ok(ref value) => Ok(value.clone()),
+        Err(Error::Unexpected(pos, reason)) => Err((pos.0, reason)),
+        ref other => panic!("{:?}", other),
+    }).collect();
+    assert_eq!(results, vec![
+        Ok(Value::Number(Number::Integer(1))),
+        Ok(Value::Number(Number::Integer(2))),
+        // Block comments can not span lines.
+        Err((42, Unexpected::LineEnd)),
+        Ok(Value::Number(Number::Integer(3))),
+        Ok(Value::Array(vec![Value::Number(Number::Integer(4)), Value::Number(Number::Integer(4))])),
+        Err((60, Unexpected::Character)),
+    ]);
+}
+
+#[test]
+fn non_finite_numbers() {
+    // As written by Python's `json.dumps`.
+    let input = b"{\"a\": NaN, \"b\": [Infinity, -Infinity, 1.5]}";
+    match Decoder::new().decode_value(input) {
+        Err(Error::Unexpected(Pos(6), Unexpected::Character)) => (),
+        other => panic!("{:?}", other),
+    }
+
+    let decoder = Decoder::with_options(Options { non_finite: true, strict: true, ..Options::default() });
+    let value = decoder.decode_value(input).unwrap();
+    assert!(value.get("a").unwrap().as_f64().unwrap().is_nan());
+    let b: Vec<_> = value.get("b").unwrap().as_array().unwrap().iter().map(|value| value.as_f64().unwrap()).collect();
+    assert_eq!(b, vec![f64::INFINITY, f64::NEG_INFINITY, 1.5]);
+    assert_eq!(decoder.decode_value_reader(Bytewise(b"[-Infinity]")).unwrap(), Value::Array(vec![Value::Number(Number::Float(f64::NEG_INFINITY))]));
+
+    // Passed on with their own kind.
+    let number = |input: &'static [u8]| {
+        let options = Options { non_finite: true, ..Options::default() };
+        match Events::with_options(SliceSource::new(input), options).nth(1) {
+            Some(Ok(Event::Number(number))) => number,
+            other => panic!("{:?}", other),
+        }
+    };
+    let infinity = number(b"[-Infinity]");
+    assert_eq!((infinity.kind, infinity.sign), (NumberKind::Infinity, false));
+    assert_eq!(number(b"[NaN]").kind, NumberKind::NaN);
+    assert_eq!(number(b"[1]").kind, NumberKind::Decimal);
+
+    // Nothing else from JSON5 is accepted.
+    assert!(decoder.decode_value(b"[0x1]").is_err());
+    assert!(decoder.decode_value(b"[Infinity1]").is_err());
+    assert!(decoder.decode_value(b"[Inf]").is_err());
+    // Signs are only allowed before `Infinity`.
+    let lenient = Decoder::with_options(Options { non_finite: true, ..Options::default() });
+    assert!(lenient.decode_value(b"[+Infinity]").is_ok());
+    for input in ["[-NaN]", "[+NaN]"].iter() {
+        match lenient.decode_value(input.as_bytes()) {
+            Err(Error::Unexpected(Pos(2), Unexpected::Number)) => (),
+            other => panic!("{}: {:?}", input, other),
+        }
+    }
+    let json5 = Decoder::with_options(Options { dialect: Dialect::Json5, ..Options::default() });
+    assert!(json5.decode_value(b"[-NaN]").unwrap()[0].as_f64().unwrap().is_nan());
+    assert_eq!(
+        decoder.decode_documents(b"NaN\n-Infinity\n", Documents::Lines).into_iter().map(|result| result.is_ok()).collect::<Vec<_>>(),
+        vec![true, true]);
+
+    // Exact conversions fail.
+    let window = Window::from(&b"[-Infinity]"[..]);
+    assert_eq!(infinity.to_decimal(window), Err(NumberError::NonFinite));
+    assert_eq!(infinity.to_i64(window), Err(NumberError::NonFinite));
+    assert_eq!(infinity.to_f64(window), Ok(f64::NEG_INFINITY));
+}
+
+/// Numbers can not contain whitespace.
+#[test]
+fn number_whitespace() {
+    for input in ["[- 1]", "[1 .5]", "[1 e5]", "[1e 5]", "[1e- 5]"].iter() {
+        assert!(parse_to_enum(input.as_bytes()).is_err(), "{}", input);
+    }
+}
+
+#[test]
+fn json_test_suite_strict() {
+    use ::std::fs;
+    use ::std::io::Read;
+
+    let mut success = true;
+
+    for path_opt in fs::read_dir("tests/data/json_test_suite/").unwrap() {
+        let dir_entry = path_opt.unwrap();
+        let file_name = dir_entry.file_name();
+        let file_name_str = file_name.to_str().unwrap();
+        let test_name = format!("==== {} ====", file_name_str);
+
+        let mut buf = Vec::new();
+        fs::File::open(dir_entry.path()).unwrap().read_to_end(&mut buf).unwrap();
+
+        let expected = match file_name_str.as_bytes()[0] {
+            b'y' => true,
+            b'n' => false,
+            _ => continue,
+        };
+        let result = parse_to_enum_print(&buf, Options::strict(), &test_name, expected);
+        if result.is_ok() != expected {
+            println!("Unexpected result: {:?}", result);
+            success = false;
+        }
+    }
+
+    assert!(success);
+}
+
 #[test]
 fn json_test_suite() {
     use ::std::fs;
@@ -379,7 +2139,7 @@ fn json_test_suite() {
 
             match file_name_str.as_bytes()[0] {
                 b'y' => {
-                    let result = parse_to_enum_print(&mut buf, &test_name, true);
+                    let result = parse_to_enum_print(&mut buf, Options::default(), &test_name, true);
                     match result {
                         Ok(_) => (),
                         Err(err) => {
@@ -393,7 +2153,7 @@ fn json_test_suite() {
                     }
                 },
                 b'n' => {
-                    let result = parse_to_enum_print(&mut buf, &test_name, false);
+                    let result = parse_to_enum_print(&mut buf, Options::default(), &test_name, false);
                     match result {
                         Ok(res) => {
                             if !can_fail {